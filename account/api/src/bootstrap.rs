@@ -0,0 +1,188 @@
+use std::env;
+
+use domain::identity::errors::Error as IdentityError;
+use domain::identity::models::entities::Role as IdentityRole;
+use domain::identity::repositories::Repository as IdentityRepository;
+use domain::identity::services::Service as IdentityService;
+use domain::user::commands::{Command as UserCommand, CommandExecutor as UserCommandExecutor};
+use domain::user::errors::Error as UserError;
+use domain::user::queries::{Query as UserQuery, QueryReader as UserQueryReader};
+use domain::user::User;
+use event_sourcing::repository::interface::ReservingRepository;
+
+use crate::container::Container;
+
+const ADMINISTRATOR: &str = "Administrator";
+
+// Grants the administrator role to the active user signed up with ACCOUNT_ADMIN_EMAIL, so that
+// the first administrator can manage the roles of others. It does nothing once an administrator
+// exists, so a role revoked later is not granted again on the next start.
+pub async fn bootstrap_administrator(container: &mut Container) {
+    let Ok(email) = env::var("ACCOUNT_ADMIN_EMAIL") else {
+        return;
+    };
+
+    grant_administrator(
+        &mut container.user_command_executor,
+        &container.user_query_reader,
+        &container.identity_service,
+        &email,
+    )
+    .await;
+}
+
+async fn grant_administrator<C, Q, I>(
+    user_command_executor: &mut UserCommandExecutor<C>,
+    user_query_reader: &UserQueryReader<Q>,
+    identity_service: &IdentityService<I>,
+    email: &str,
+) where
+    C: ReservingRepository<User>,
+    Q: ReservingRepository<User>,
+    I: IdentityRepository,
+    IdentityError: From<I::Error>,
+{
+    match identity_service
+        .exists_with_role(IdentityRole::Administrator)
+        .await
+    {
+        Ok(false) => {}
+        Ok(true) => return,
+        Err(error) => {
+            log::error!("Failed to find existing administrators: {}", error);
+            return;
+        }
+    }
+
+    let user = match user_query_reader
+        .read(UserQuery::GetUserByEmail {
+            email: email.to_string(),
+        })
+        .await
+    {
+        Ok(user) => user,
+        Err(error) => {
+            log::warn!("Failed to find administrator {}: {}", email, error);
+            return;
+        }
+    };
+    if !user.is_active() {
+        log::warn!(
+            "Failed to grant administrator to {} because it is not verified or withdrawn",
+            email
+        );
+        return;
+    }
+    let id = user.id;
+
+    let command = UserCommand::GrantRole {
+        id,
+        role: String::from(ADMINISTRATOR),
+    };
+    match user_command_executor.execute(command).await {
+        Ok(()) | Err(UserError::RoleAlreadyGranted(..)) => {}
+        Err(error) => {
+            log::error!("Failed to grant administrator to {}: {}", email, error);
+            return;
+        }
+    }
+
+    // the identity may lag behind the aggregate if an earlier run failed halfway
+    match identity_service
+        .change_role(id, IdentityRole::Administrator)
+        .await
+    {
+        Ok(_) => log::info!("User {} ({}) is an administrator", id, email),
+        Err(error) => log::error!("Failed to change role of identity {}: {}", id, error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use domain::identity::models::entities::User as IdentityUser;
+    use domain::identity::repositories::MemoryRepository as IdentityMemoryRepository;
+    use domain::mailer::MemoryMailer;
+    use domain::secrets::{configure, Secrets};
+    use event_sourcing::repository::memory::MemoryRepository;
+    use uuid::Uuid;
+
+    use crate::bootstrap::*;
+
+    #[tokio::test]
+    async fn bootstrap_catches_up_identity_left_behind_by_promoted_user() {
+        // another test may have configured them already
+        let _ = configure(Secrets {
+            access_token: String::from("access-token-secret"),
+            refresh_token: String::from("refresh-token-secret"),
+            verification_token: String::from("verification-token-secret"),
+            email_change_token: String::from("email-change-token-secret"),
+        });
+        let repository = MemoryRepository::default();
+        let mailer = MemoryMailer::default();
+        let mut user_command_executor =
+            UserCommandExecutor::new(repository.clone(), Arc::new(mailer.clone()));
+        let user_query_reader = UserQueryReader::new(repository.clone());
+        let identity_repository = IdentityMemoryRepository::default();
+        let identity_service = IdentityService::new(identity_repository.clone());
+
+        let id = Uuid::new_v4();
+        let email = "peppydays@gmail.com";
+        user_command_executor
+            .execute(UserCommand::RegisterUser {
+                id,
+                name: String::from("Arine"),
+                password: String::from("welcome"),
+                email: String::from(email),
+                language: String::from("en"),
+            })
+            .await
+            .unwrap();
+        identity_service
+            .register_identity(IdentityUser::new(id, IdentityRole::Member))
+            .await
+            .unwrap();
+        user_command_executor
+            .execute(UserCommand::IssueVerification { id })
+            .await
+            .unwrap();
+        let token = mailer.sent()[0]
+            .body
+            .trim_end()
+            .lines()
+            .last()
+            .unwrap()
+            .to_string();
+        user_command_executor
+            .execute(UserCommand::VerifyEmail { token })
+            .await
+            .unwrap();
+        // an earlier run promoted the user but failed before its identity followed
+        user_command_executor
+            .execute(UserCommand::GrantRole {
+                id,
+                role: String::from(ADMINISTRATOR),
+            })
+            .await
+            .unwrap();
+
+        grant_administrator(
+            &mut user_command_executor,
+            &user_query_reader,
+            &identity_service,
+            email,
+        )
+        .await;
+
+        let identity = identity_repository
+            .find_by_user_id(&id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            identity.user,
+            IdentityUser::new(id, IdentityRole::Administrator)
+        );
+    }
+}
@@ -1,17 +1,22 @@
 use std::env;
+use std::sync::Arc;
 
 use sqlx::mysql::MySqlPoolOptions;
 
 use domain::identity::services::Service as IdentityService;
+use domain::mailer::Mailer;
 use domain::user::{
     commands::CommandExecutor as UserCommandExecutor, queries::QueryReader as UserQueryReader,
+    register_schemas,
 };
 use event_sourcing::repository::mysql::MySqlRepository as UserMySqlRepository;
+use event_sourcing::schema::{SchemaRegistry, ValidatingRepository};
+use infrastructure::mailers::{file::FileMailer, smtp::SmtpMailer};
 use infrastructure::repositories::identity::MySqlRepository as IdentityMySqlRepository;
 
 #[derive(Clone)]
 pub struct Container {
-    pub user_command_executor: UserCommandExecutor<UserMySqlRepository>,
+    pub user_command_executor: UserCommandExecutor<ValidatingRepository<UserMySqlRepository>>,
     pub user_query_reader: UserQueryReader<UserMySqlRepository>,
     pub identity_service: IdentityService<IdentityMySqlRepository>,
 }
@@ -28,7 +33,13 @@ impl Container {
                 .await
                 .unwrap(),
         );
-        let user_command_executor = UserCommandExecutor::new(user_repository.clone());
+        // events are validated against their schemas in debug builds
+        let mut schema_registry = SchemaRegistry::default();
+        register_schemas(&mut schema_registry).unwrap();
+        let user_command_executor = UserCommandExecutor::new(
+            ValidatingRepository::new(user_repository.clone(), Arc::new(schema_registry)),
+            get_mailer(),
+        );
         let user_query_reader = UserQueryReader::new(user_repository.clone());
 
         let identity_repository = IdentityMySqlRepository::new(
@@ -68,3 +79,24 @@ fn get_database_url() -> String {
         username, password, host, port, schema
     )
 }
+
+// Mails are written to a local file unless an SMTP host is configured
+fn get_mailer() -> Arc<dyn Mailer> {
+    match env::var("ACCOUNT_SMTP_HOST") {
+        Ok(host) => {
+            let port = env::var("ACCOUNT_SMTP_PORT")
+                .unwrap_or(String::from("587"))
+                .parse::<u16>()
+                .unwrap();
+            let username = env::var("ACCOUNT_SMTP_USERNAME").unwrap_or_default();
+            let password = env::var("ACCOUNT_SMTP_PASSWORD").unwrap_or_default();
+            let from = env::var("ACCOUNT_MAIL_FROM").unwrap_or(String::from("noreply@localhost"));
+
+            Arc::new(SmtpMailer::new(&host, port, username, password, &from).unwrap())
+        }
+        Err(_) => {
+            let path = env::var("ACCOUNT_MAIL_FILE").unwrap_or(String::from("mails.log"));
+            Arc::new(FileMailer::new(path))
+        }
+    }
+}
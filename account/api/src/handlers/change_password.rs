@@ -0,0 +1,80 @@
+use axum::{extract::State, http::StatusCode, Extension};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use domain::identity::errors::Error as IdentityError;
+use domain::identity::models::entities::User as IdentityUser;
+use domain::user::commands::Command as UserCommand;
+use domain::user::errors::Error as UserError;
+
+use crate::extractors::Json;
+use crate::{container::Container, errors::Error};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Request {
+    current_password: String,
+    new_password: String,
+}
+
+pub async fn handle(
+    Extension(identity_user): Extension<IdentityUser>,
+    State(mut container): State<Container>,
+    Json(request): Json<Request>,
+) -> Result<StatusCode, Error> {
+    change_password(&mut container, &identity_user, request).await?;
+    invalidate_tokens(&container, identity_user.id).await?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn change_password(
+    container: &mut Container,
+    identity_user: &IdentityUser,
+    request: Request,
+) -> Result<(), Error> {
+    let command = UserCommand::ChangePassword {
+        id: identity_user.id,
+        current_password: request.current_password,
+        new_password: request.new_password,
+    };
+
+    container
+        .user_command_executor
+        .execute(command)
+        .await
+        .map_err(|error| {
+            let message = error.to_string();
+            log::error!("Failed to change password: {}", &message);
+
+            match error {
+                UserError::InvalidCredential(..) => Error::new(
+                    StatusCode::UNAUTHORIZED,
+                    "Failed to change password due to the invalid credential",
+                ),
+                UserError::UserNotFound(..) => Error::new(StatusCode::NOT_FOUND, &message),
+                UserError::InvalidPassword => Error::new(StatusCode::BAD_REQUEST, &message),
+                UserError::UserAlreadyWithdrawn(..) => Error::new(StatusCode::FORBIDDEN, &message),
+                _ => Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message),
+            }
+        })
+}
+
+async fn invalidate_tokens(container: &Container, id: Uuid) -> Result<(), Error> {
+    container
+        .identity_service
+        .invalidate_tokens(id)
+        .await
+        .map_err(|error| {
+            let message = error.to_string();
+            log::error!(
+                "Failed to invalidate tokens after password change: {}",
+                &message
+            );
+
+            match error {
+                IdentityError::IdentityNotFound(..) => Error::new(StatusCode::NOT_FOUND, &message),
+                _ => Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message),
+            }
+        })
+}
@@ -1,11 +1,19 @@
+use std::time::Duration;
+
 use axum::http::StatusCode;
+use tokio::time::sleep;
 use uuid::Uuid;
 
 use domain::identity::errors::Error as IdentityError;
 use domain::identity::models::entities::User as IdentityUser;
+use domain::user::errors::Error as UserError;
+use domain::user::queries::Query as UserQuery;
 
+use crate::container::Container;
 use crate::errors::Error;
 
+const IDENTITY_SYNC_ATTEMPTS: u32 = 5;
+
 pub fn parse_identity_user(id: Uuid, role: String) -> Result<IdentityUser, Error> {
     Ok(IdentityUser::new(
         id,
@@ -17,3 +25,58 @@ pub fn parse_identity_user(id: Uuid, role: String) -> Result<IdentityUser, Error
         })?,
     ))
 }
+
+// The identity of a user as the user aggregate currently sees it
+pub async fn get_identity_user(container: &Container, id: Uuid) -> Result<IdentityUser, Error> {
+    let user = container
+        .user_query_reader
+        .read(UserQuery::GetUser { id })
+        .await
+        .map_err(|error| {
+            let message = error.to_string();
+            log::error!("Failed to get user: {}", &message);
+
+            match error {
+                UserError::UserNotFound(..) => Error::new(StatusCode::NOT_FOUND, &message),
+                UserError::UserAlreadyWithdrawn(..) => Error::new(StatusCode::FORBIDDEN, &message),
+                _ => Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message),
+            }
+        })?;
+
+    parse_identity_user(id, user.role.to_string())
+}
+
+// Brings the identity to the role the user aggregate holds. The aggregate is read again on every
+// attempt, so the identity ends up with the latest role whichever way a concurrent change lands.
+pub async fn sync_identity_role(container: &Container, id: Uuid) -> Result<(), Error> {
+    let mut attempt = 1;
+
+    loop {
+        let identity_user = get_identity_user(container, id).await?;
+        let error = match container
+            .identity_service
+            .change_role(id, identity_user.role)
+            .await
+        {
+            Ok(_) => return Ok(()),
+            Err(error) => error,
+        };
+
+        let message = error.to_string();
+        match error {
+            IdentityError::IdentityNotFound(..) => {
+                log::error!("Failed to change role of identity: {}", &message);
+                return Err(Error::new(StatusCode::NOT_FOUND, &message));
+            }
+            _ if attempt < IDENTITY_SYNC_ATTEMPTS => {
+                log::warn!("Failed to change role of identity, retrying: {}", &message);
+                sleep(Duration::from_millis(100 << attempt)).await;
+                attempt += 1;
+            }
+            _ => {
+                log::error!("Failed to change role of identity: {}", &message);
+                return Err(Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message));
+            }
+        }
+    }
+}
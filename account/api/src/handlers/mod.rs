@@ -1,7 +1,18 @@
+pub mod change_password;
 pub mod check_health;
-mod common;
+pub(crate) mod common;
+pub mod confirm_email_change;
 pub mod get_user;
+pub mod grant_role;
 pub mod refresh_tokens;
+pub mod request_email_change;
+pub mod request_password_reset;
+pub mod resend_verification;
+pub mod reset_password;
+pub mod revoke_role;
 pub mod sign_in;
 pub mod sign_out;
 pub mod sign_up;
+pub mod update_profile;
+pub mod verify_email;
+pub mod withdraw;
@@ -0,0 +1,45 @@
+use axum::{extract::State, http::StatusCode, Extension};
+use serde::Deserialize;
+
+use domain::identity::models::entities::User as IdentityUser;
+use domain::user::commands::Command as UserCommand;
+use domain::user::errors::Error as UserError;
+
+use crate::extractors::Json;
+use crate::{container::Container, errors::Error};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Request {
+    email: String,
+}
+
+pub async fn handle(
+    Extension(identity_user): Extension<IdentityUser>,
+    State(mut container): State<Container>,
+    Json(request): Json<Request>,
+) -> Result<StatusCode, Error> {
+    let command = UserCommand::RequestEmailChange {
+        id: identity_user.id,
+        email: request.email,
+    };
+
+    container
+        .user_command_executor
+        .execute(command)
+        .await
+        .map_err(|error| {
+            let message = error.to_string();
+            log::error!("Failed to request email change: {}", &message);
+
+            match error {
+                UserError::InvalidEmail(..) => Error::new(StatusCode::BAD_REQUEST, &message),
+                UserError::EmailAlreadyRegistered(..) => Error::new(StatusCode::CONFLICT, &message),
+                UserError::UserNotFound(..) => Error::new(StatusCode::NOT_FOUND, &message),
+                UserError::UserAlreadyWithdrawn(..) => Error::new(StatusCode::FORBIDDEN, &message),
+                _ => Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message),
+            }
+        })?;
+
+    Ok(StatusCode::ACCEPTED)
+}
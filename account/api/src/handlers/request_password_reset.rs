@@ -0,0 +1,39 @@
+use axum::{extract::State, http::StatusCode};
+use serde::Deserialize;
+
+use domain::user::commands::Command as UserCommand;
+use domain::user::errors::Error as UserError;
+
+use crate::extractors::Json;
+use crate::{container::Container, errors::Error};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Request {
+    email: String,
+}
+
+// Responds the same whether the user exists or not, so that accounts cannot be probed
+pub async fn handle(
+    State(mut container): State<Container>,
+    Json(request): Json<Request>,
+) -> Result<StatusCode, Error> {
+    let command = UserCommand::RequestPasswordReset {
+        email: request.email,
+    };
+
+    match container.user_command_executor.execute(command).await {
+        Ok(_) => Ok(StatusCode::ACCEPTED),
+        Err(error) => {
+            let message = error.to_string();
+            log::error!("Failed to request password reset: {}", &message);
+
+            match error {
+                UserError::EmailNotFound(..)
+                | UserError::UserNotFound(..)
+                | UserError::UserAlreadyWithdrawn(..) => Ok(StatusCode::ACCEPTED),
+                _ => Err(Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message)),
+            }
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use axum::{extract::State, http::StatusCode, Extension};
+
+use domain::identity::models::entities::User as IdentityUser;
+use domain::user::commands::Command as UserCommand;
+use domain::user::errors::Error as UserError;
+
+use crate::{container::Container, errors::Error};
+
+pub async fn handle(
+    Extension(identity_user): Extension<IdentityUser>,
+    State(mut container): State<Container>,
+) -> Result<StatusCode, Error> {
+    let command = UserCommand::IssueVerification {
+        id: identity_user.id,
+    };
+
+    container
+        .user_command_executor
+        .execute(command)
+        .await
+        .map_err(|error| {
+            let message = error.to_string();
+            log::error!("Failed to resend email verification: {}", &message);
+
+            match error {
+                UserError::VerificationThrottled(..) => {
+                    Error::new(StatusCode::TOO_MANY_REQUESTS, &message)
+                }
+                UserError::EmailAlreadyVerified(..) => Error::new(StatusCode::CONFLICT, &message),
+                UserError::UserNotFound(..) => Error::new(StatusCode::NOT_FOUND, &message),
+                UserError::UserAlreadyWithdrawn(..) => Error::new(StatusCode::FORBIDDEN, &message),
+                _ => Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message),
+            }
+        })?;
+
+    Ok(StatusCode::ACCEPTED)
+}
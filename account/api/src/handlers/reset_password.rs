@@ -0,0 +1,74 @@
+use axum::{extract::State, http::StatusCode};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use domain::identity::errors::Error as IdentityError;
+use domain::user::commands::Command as UserCommand;
+use domain::user::errors::Error as UserError;
+use domain::user::parse_reset_token;
+
+use crate::extractors::Json;
+use crate::{container::Container, errors::Error};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Request {
+    token: String,
+    new_password: String,
+}
+
+pub async fn handle(
+    State(mut container): State<Container>,
+    Json(request): Json<Request>,
+) -> Result<StatusCode, Error> {
+    let (id, _) = parse_reset_token(&request.token)
+        .map_err(|error| Error::new(StatusCode::BAD_REQUEST, error.to_string()))?;
+
+    reset_password(&mut container, request).await?;
+    invalidate_tokens(&container, id).await?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn reset_password(container: &mut Container, request: Request) -> Result<(), Error> {
+    let command = UserCommand::ResetPassword {
+        token: request.token,
+        new_password: request.new_password,
+    };
+
+    container
+        .user_command_executor
+        .execute(command)
+        .await
+        .map_err(|error| {
+            let message = error.to_string();
+            log::error!("Failed to reset password: {}", &message);
+
+            match error {
+                UserError::InvalidResetToken | UserError::InvalidPassword => {
+                    Error::new(StatusCode::BAD_REQUEST, &message)
+                }
+                UserError::UserAlreadyWithdrawn(..) => Error::new(StatusCode::FORBIDDEN, &message),
+                _ => Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message),
+            }
+        })
+}
+
+async fn invalidate_tokens(container: &Container, id: Uuid) -> Result<(), Error> {
+    container
+        .identity_service
+        .invalidate_tokens(id)
+        .await
+        .map_err(|error| {
+            let message = error.to_string();
+            log::error!(
+                "Failed to invalidate tokens after password reset: {}",
+                &message
+            );
+
+            match error {
+                IdentityError::IdentityNotFound(..) => Error::new(StatusCode::NOT_FOUND, &message),
+                _ => Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message),
+            }
+        })
+}
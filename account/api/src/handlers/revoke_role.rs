@@ -0,0 +1,51 @@
+use axum::{extract::State, http::StatusCode};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use domain::user::commands::Command as UserCommand;
+use domain::user::errors::Error as UserError;
+
+use crate::extractors::Json;
+use crate::handlers::common::sync_identity_role;
+use crate::{container::Container, errors::Error};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Request {
+    id: Uuid,
+    role: String,
+}
+
+pub async fn handle(
+    State(mut container): State<Container>,
+    Json(request): Json<Request>,
+) -> Result<StatusCode, Error> {
+    let result = revoke_role(&mut container, request.id, request.role).await;
+
+    // a retried request still brings along an identity that an earlier one left behind
+    if let Ok(()) | Err(UserError::RoleNotGranted(..)) = result {
+        sync_identity_role(&container, request.id).await?;
+    }
+    result.map_err(|error| {
+        let message = error.to_string();
+        log::error!("Failed to revoke role: {}", &message);
+
+        match error {
+            UserError::InvalidRole(..) | UserError::RoleNotRevocable(..) => {
+                Error::new(StatusCode::BAD_REQUEST, &message)
+            }
+            UserError::RoleNotGranted(..) => Error::new(StatusCode::CONFLICT, &message),
+            UserError::UserNotFound(..) => Error::new(StatusCode::NOT_FOUND, &message),
+            UserError::UserAlreadyWithdrawn(..) => Error::new(StatusCode::FORBIDDEN, &message),
+            _ => Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message),
+        }
+    })?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn revoke_role(container: &mut Container, id: Uuid, role: String) -> Result<(), UserError> {
+    let command = UserCommand::RevokeRole { id, role };
+
+    container.user_command_executor.execute(command).await
+}
@@ -1,9 +1,8 @@
 use axum::{extract::State, http::StatusCode};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 use domain::identity::errors::Error as IdentityError;
-use domain::identity::models::entities::Tokens as IdentityTokens;
+use domain::identity::models::entities::{Tokens as IdentityTokens, User as IdentityUser};
 use domain::user::errors::Error as UserError;
 use domain::user::queries::Query as UserQuery;
 
@@ -11,11 +10,10 @@ use crate::extractors::Json;
 use crate::handlers::common::parse_identity_user;
 use crate::{container::Container, errors::Error};
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Request {
-    id: Uuid,
-    role: String,
+    email: String,
     password: String,
 }
 
@@ -30,8 +28,8 @@ pub async fn handle(
     State(mut container): State<Container>,
     Json(request): Json<Request>,
 ) -> Result<Json<Response>, Error> {
-    verify_credential(&container, request.clone()).await?;
-    let tokens = issue_tokens(&mut container, request).await?;
+    let identity_user = verify_credential(&container, request).await?;
+    let tokens = issue_tokens(&mut container, identity_user).await?;
 
     Ok(Json(Response {
         access_token: tokens.access_token.into(),
@@ -39,13 +37,14 @@ pub async fn handle(
     }))
 }
 
-async fn verify_credential(container: &Container, request: Request) -> Result<(), Error> {
+// the role comes from the aggregate rather than from the client
+async fn verify_credential(container: &Container, request: Request) -> Result<IdentityUser, Error> {
     let query = UserQuery::VerifyCredential {
-        id: request.id,
+        email: request.email,
         password: request.password,
     };
 
-    container
+    let user = container
         .user_query_reader
         .read(query)
         .await
@@ -54,22 +53,24 @@ async fn verify_credential(container: &Container, request: Request) -> Result<()
             log::error!("Failed to sign in: {}", &message);
 
             match error {
-                UserError::InvalidCredential(..) | UserError::UserNotFound(..) => Error::new(
+                UserError::InvalidCredential(..)
+                | UserError::UserNotFound(..)
+                | UserError::EmailNotFound(..) => Error::new(
                     StatusCode::UNAUTHORIZED,
                     "Failed to sign in due to the invalid credential",
                 ),
+                UserError::UserAlreadyWithdrawn(..) => Error::new(StatusCode::FORBIDDEN, &message),
                 _ => Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message),
             }
-        })
-        .map(|_| ())
+        })?;
+
+    parse_identity_user(user.id, user.role.to_string())
 }
 
 async fn issue_tokens(
     container: &mut Container,
-    request: Request,
+    identity_user: IdentityUser,
 ) -> Result<IdentityTokens, Error> {
-    let identity_user = parse_identity_user(request.id, request.role)?;
-
     container
         .identity_service
         .issue_tokens(identity_user)
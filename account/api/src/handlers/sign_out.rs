@@ -11,7 +11,7 @@ pub async fn handle(
 ) -> Result<StatusCode, Error> {
     container
         .identity_service
-        .invalidate_tokens(identity_user)
+        .invalidate_tokens(identity_user.id)
         .await
         .map_err(|error| {
             let message = error.to_string();
@@ -34,10 +34,20 @@ pub async fn handle(
 
     register_user(&mut container, request, id).await?;
     register_identity(&mut container, id).await?;
+    issue_verification(&mut container, id).await;
 
     Ok(Json(Response { id }))
 }
 
+// The user can ask for another verification mail, so a failure here does not fail the sign-up
+async fn issue_verification(container: &mut Container, id: Uuid) {
+    let command = UserCommand::IssueVerification { id };
+
+    if let Err(error) = container.user_command_executor.execute(command).await {
+        log::warn!("Failed to issue email verification: {}", error);
+    }
+}
+
 async fn register_identity(container: &mut Container, id: Uuid) -> Result<(), Error> {
     let identity_user = parse_identity_user(id, String::from("Member"))?;
 
@@ -78,7 +88,7 @@ async fn register_user(container: &mut Container, request: Request, id: Uuid) ->
             log::error!("Failed to register user: {}", &message);
 
             match error {
-                UserError::UserAlreadyRegistered(..) => {
+                UserError::UserAlreadyRegistered(..) | UserError::EmailAlreadyRegistered(..) => {
                     Error::new(StatusCode::CONFLICT, error.to_string())
                 }
                 _ => Error::new(StatusCode::INTERNAL_SERVER_ERROR, error.to_string()),
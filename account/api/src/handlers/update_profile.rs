@@ -0,0 +1,59 @@
+use axum::{extract::State, http::StatusCode, Extension};
+use serde::Deserialize;
+
+use domain::identity::models::entities::User as IdentityUser;
+use domain::user::commands::Command as UserCommand;
+use domain::user::errors::Error as UserError;
+
+use crate::extractors::Json;
+use crate::{container::Container, errors::Error};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Request {
+    name: Option<String>,
+    language: Option<String>,
+}
+
+pub async fn handle(
+    Extension(identity_user): Extension<IdentityUser>,
+    State(mut container): State<Container>,
+    Json(request): Json<Request>,
+) -> Result<StatusCode, Error> {
+    if let Some(name) = request.name {
+        let command = UserCommand::ChangeName {
+            id: identity_user.id,
+            name,
+        };
+        execute(&mut container, command).await?;
+    }
+    if let Some(language) = request.language {
+        let command = UserCommand::ChangeLanguage {
+            id: identity_user.id,
+            language,
+        };
+        execute(&mut container, command).await?;
+    }
+
+    Ok(StatusCode::OK)
+}
+
+async fn execute(container: &mut Container, command: UserCommand) -> Result<(), Error> {
+    container
+        .user_command_executor
+        .execute(command)
+        .await
+        .map_err(|error| {
+            let message = error.to_string();
+            log::error!("Failed to update profile: {}", &message);
+
+            match error {
+                UserError::InvalidName(..) | UserError::InvalidLanguage(..) => {
+                    Error::new(StatusCode::UNPROCESSABLE_ENTITY, &message)
+                }
+                UserError::UserNotFound(..) => Error::new(StatusCode::NOT_FOUND, &message),
+                UserError::UserAlreadyWithdrawn(..) => Error::new(StatusCode::FORBIDDEN, &message),
+                _ => Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message),
+            }
+        })
+}
@@ -0,0 +1,43 @@
+use axum::{extract::State, http::StatusCode};
+use serde::Deserialize;
+
+use domain::user::commands::Command as UserCommand;
+use domain::user::errors::Error as UserError;
+
+use crate::extractors::Json;
+use crate::{container::Container, errors::Error};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Request {
+    token: String,
+}
+
+pub async fn handle(
+    State(mut container): State<Container>,
+    Json(request): Json<Request>,
+) -> Result<StatusCode, Error> {
+    let command = UserCommand::VerifyEmail {
+        token: request.token,
+    };
+
+    container
+        .user_command_executor
+        .execute(command)
+        .await
+        .map_err(|error| {
+            let message = error.to_string();
+            log::error!("Failed to verify email: {}", &message);
+
+            match error {
+                UserError::InvalidVerificationToken => {
+                    Error::new(StatusCode::BAD_REQUEST, &message)
+                }
+                UserError::EmailAlreadyVerified(..) => Error::new(StatusCode::CONFLICT, &message),
+                UserError::UserAlreadyWithdrawn(..) => Error::new(StatusCode::FORBIDDEN, &message),
+                _ => Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message),
+            }
+        })?;
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,73 @@
+use axum::{extract::State, http::StatusCode, Extension};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use domain::identity::errors::Error as IdentityError;
+use domain::identity::models::entities::User as IdentityUser;
+use domain::user::commands::Command as UserCommand;
+use domain::user::errors::Error as UserError;
+
+use crate::extractors::Json;
+use crate::{container::Container, errors::Error};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Request {
+    reason: String,
+}
+
+pub async fn handle(
+    Extension(identity_user): Extension<IdentityUser>,
+    State(mut container): State<Container>,
+    Json(request): Json<Request>,
+) -> Result<StatusCode, Error> {
+    withdraw_user(&mut container, &identity_user, request).await?;
+    invalidate_tokens(&container, identity_user.id).await?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn withdraw_user(
+    container: &mut Container,
+    identity_user: &IdentityUser,
+    request: Request,
+) -> Result<(), Error> {
+    let command = UserCommand::WithdrawUser {
+        id: identity_user.id,
+        reason: request.reason,
+    };
+
+    container
+        .user_command_executor
+        .execute(command)
+        .await
+        .map_err(|error| {
+            let message = error.to_string();
+            log::error!("Failed to withdraw user: {}", &message);
+
+            match error {
+                UserError::UserNotFound(..) => Error::new(StatusCode::NOT_FOUND, &message),
+                UserError::UserAlreadyWithdrawn(..) => Error::new(StatusCode::CONFLICT, &message),
+                _ => Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message),
+            }
+        })
+}
+
+async fn invalidate_tokens(container: &Container, id: Uuid) -> Result<(), Error> {
+    container
+        .identity_service
+        .invalidate_tokens(id)
+        .await
+        .map_err(|error| {
+            let message = error.to_string();
+            log::error!(
+                "Failed to invalidate tokens of withdrawn user: {}",
+                &message
+            );
+
+            match error {
+                IdentityError::IdentityNotFound(..) => Error::new(StatusCode::NOT_FOUND, &message),
+                _ => Error::new(StatusCode::INTERNAL_SERVER_ERROR, &message),
+            }
+        })
+}
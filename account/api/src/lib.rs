@@ -1,3 +1,4 @@
+pub mod bootstrap;
 pub mod container;
 mod errors;
 mod extractors;
@@ -3,13 +3,16 @@ use std::net::{Ipv4Addr, SocketAddr};
 
 use tokio::net::TcpListener;
 
-use api::{container::get_container, router};
+use api::{bootstrap::bootstrap_administrator, container::get_container, router};
+use domain::secrets::{configure as configure_secrets, Secrets};
 
 #[tokio::main]
 async fn main() {
     env_logger::init();
+    configure_secrets(Secrets::from_env().unwrap()).unwrap();
 
-    let container = get_container().await;
+    let mut container = get_container().await;
+    bootstrap_administrator(&mut container).await;
     let app = router::create_router(container);
     let port = match env::var("ACCOUNT_APPLICATION_PORT") {
         Ok(p) => p.parse::<u16>().unwrap(),
@@ -1,17 +1,22 @@
 use axum::{
     body::Body,
-    extract::State,
+    extract::{Path, State},
     http::{Request, StatusCode},
     middleware::Next,
     response::Response,
+    Extension,
 };
 use axum_extra::{
     headers::{authorization::Bearer, Authorization},
     TypedHeader,
 };
 
+use uuid::Uuid;
+
+use crate::handlers::common::get_identity_user;
 use crate::{container::Container, errors::Error};
 use domain::identity::errors::Error as IdentityError;
+use domain::identity::models::entities::{Role as IdentityRole, User as IdentityUser};
 
 pub async fn require_authentication(
     TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
@@ -33,3 +38,55 @@ pub async fn require_authentication(
     request.extensions_mut().insert(identity_user);
     Ok(next.run(request).await)
 }
+
+// The policies below read the user put by require_authentication, so they have to be layered
+// inside it. Roles are read from the user aggregate rather than the access token, so that a
+// revoked role stops granting access before the token expires.
+
+pub async fn require_role(
+    State((container, role)): State<(Container, IdentityRole)>,
+    Extension(identity_user): Extension<IdentityUser>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, Error> {
+    let current_user = get_identity_user(&container, identity_user.id).await?;
+    if current_user.role != role {
+        return Err(forbidden(&current_user));
+    }
+
+    Ok(next.run(request).await)
+}
+
+// Lets users reach the resources under their own id, and administrators reach everyone's
+pub async fn require_self_or_administrator(
+    State(container): State<Container>,
+    Path(id): Path<Uuid>,
+    Extension(identity_user): Extension<IdentityUser>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, Error> {
+    if identity_user.id != id {
+        let current_user = get_identity_user(&container, identity_user.id).await?;
+        if current_user.role != IdentityRole::Administrator {
+            return Err(forbidden(&current_user));
+        }
+    }
+
+    Ok(next.run(request).await)
+}
+
+fn forbidden(identity_user: &IdentityUser) -> Error {
+    log::warn!(
+        "Denied access of user {} with role {:?}",
+        identity_user.id,
+        identity_user.role
+    );
+
+    Error::new(
+        StatusCode::FORBIDDEN,
+        format!(
+            "User {} is not allowed to access the resource",
+            identity_user.id
+        ),
+    )
+}
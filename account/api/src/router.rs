@@ -1,25 +1,71 @@
 use axum::middleware::from_fn_with_state;
-use axum::routing::{get, post};
+use axum::routing::{get, patch, post};
 use axum::Router;
 
+use domain::identity::models::entities::Role as IdentityRole;
+
 use crate::container::Container;
 use crate::handlers::*;
 use crate::middleware::*;
 
 pub fn create_router(container: Container) -> Router {
-    Router::new()
+    let administrator_routes = Router::new()
+        .route("/account/user/grant-role", post(grant_role::handle))
+        .route("/account/user/revoke-role", post(revoke_role::handle))
+        .route_layer(from_fn_with_state(
+            (container.clone(), IdentityRole::Administrator),
+            require_role,
+        ));
+
+    let self_or_administrator_routes = Router::new()
+        .route("/account/user/get-user/:id", get(get_user::handle))
+        .route_layer(from_fn_with_state(
+            container.clone(),
+            require_self_or_administrator,
+        ));
+
+    let authenticated_routes = Router::new()
         .route("/account/user/sign-out", post(sign_out::handle))
+        .route("/account/user/withdraw", post(withdraw::handle))
+        .route(
+            "/account/user/change-password",
+            post(change_password::handle),
+        )
+        .route("/account/user/profile", patch(update_profile::handle))
+        .route(
+            "/account/user/resend-verification",
+            post(resend_verification::handle),
+        )
+        .route(
+            "/account/user/request-email-change",
+            post(request_email_change::handle),
+        )
+        .merge(administrator_routes)
+        .merge(self_or_administrator_routes)
         .route_layer(from_fn_with_state(
             container.clone(),
             require_authentication,
-        ))
+        ));
+
+    // routes below are public, as they are the ways to get authenticated
+    Router::new()
+        .merge(authenticated_routes)
         .route(
             "/account/identity/refresh-tokens",
             post(refresh_tokens::handle),
         )
         .route("/account/user/sign-in", post(sign_in::handle))
-        .route("/account/user/get-user/:id", get(get_user::handle))
         .route("/account/user/sign-up", post(sign_up::handle))
+        .route("/account/user/verify-email", post(verify_email::handle))
+        .route(
+            "/account/user/request-password-reset",
+            post(request_password_reset::handle),
+        )
+        .route("/account/user/reset-password", post(reset_password::handle))
+        .route(
+            "/account/user/confirm-email-change",
+            post(confirm_email_change::handle),
+        )
         .with_state(container)
         .route("/account/user/check-health", get(check_health::handle))
 }
@@ -8,11 +8,10 @@ use jsonwebtoken::{
 
 use crate::identity::errors::Error;
 use crate::identity::models::entities::*;
+use crate::secrets;
 
 const ACCESS_TOKEN_DURATION_IN_DAYS: u64 = 1;
-const ACCESS_TOKEN_SECRET: &str = "ACCOUNT_ACCESS_TOKEN_SECRET";
 const REFRESH_TOKEN_DURATION_IN_DAYS: u64 = 90;
-const REFRESH_TOKEN_SECRET: &str = "ACCOUNT_REFRESH_TOKEN_SECRET";
 
 impl Identity {
     pub async fn issue_tokens(&mut self) -> Result<(), Error> {
@@ -39,7 +38,7 @@ impl Identity {
             id: self.user.id,
             role: self.user.role.clone(),
         };
-        let key = JwtEncodingKey::from_secret(ACCESS_TOKEN_SECRET.as_ref());
+        let key = JwtEncodingKey::from_secret(secrets::get().access_token.as_ref());
 
         jwt_encode(&header, &claims, &key)
             .map_err(Error::TokensCreationFailed)
@@ -59,7 +58,7 @@ impl Identity {
                 .as_secs()
                 + (REFRESH_TOKEN_DURATION_IN_DAYS * 24 * 60 * 60),
         };
-        let key = JwtEncodingKey::from_secret(REFRESH_TOKEN_SECRET.as_ref());
+        let key = JwtEncodingKey::from_secret(secrets::get().refresh_token.as_ref());
 
         jwt_encode(&header, &claims, &key)
             .map_err(Error::TokensCreationFailed)
@@ -79,7 +78,7 @@ impl Identity {
 
         jwt_decode::<RefreshTokenClaims>(
             &refresh_token.0,
-            &JwtDecodingKey::from_secret(REFRESH_TOKEN_SECRET.as_ref()),
+            &JwtDecodingKey::from_secret(secrets::get().refresh_token.as_ref()),
             &JwtValidation::new(JwtAlgorithm::HS256),
         )
         .map_err(Error::TokensValidationFailed)?;
@@ -97,7 +96,7 @@ impl Identity {
     pub async fn verify_access_token(access_token: &AccessToken) -> Result<User, Error> {
         jwt_decode::<AccessTokenClaims>(
             &access_token.0,
-            &JwtDecodingKey::from_secret(ACCESS_TOKEN_SECRET.as_ref()),
+            &JwtDecodingKey::from_secret(secrets::get().access_token.as_ref()),
             &JwtValidation::new(JwtAlgorithm::HS256),
         )
         .map_err(Error::TokensValidationFailed)
@@ -197,4 +196,24 @@ mod tests {
             Error::TokensValidationFailed { .. }
         ));
     }
+
+    #[tokio::test]
+    async fn access_token_signed_with_another_secret_fails_validation() {
+        let claims = AccessTokenClaims {
+            iat: 0,
+            exp: u64::MAX,
+            id: Uuid::new_v4(),
+            role: Role::Administrator,
+        };
+        let forged_token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::HS256),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(b"ACCOUNT_ACCESS_TOKEN_SECRET"),
+        )
+        .unwrap();
+
+        let result = Identity::verify_access_token(&AccessToken(forged_token)).await;
+
+        assert!(matches!(result, Err(Error::TokensValidationFailed(..))));
+    }
 }
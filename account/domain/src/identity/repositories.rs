@@ -2,9 +2,10 @@ use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
 use async_trait::async_trait;
+use uuid::Uuid;
 
 use crate::identity::models::entities::Identity;
-use crate::identity::models::entities::User;
+use crate::identity::models::entities::{Role, User};
 
 #[async_trait]
 pub trait Repository: Send + Sync {
@@ -12,6 +13,12 @@ pub trait Repository: Send + Sync {
 
     async fn save(&self, identity: Identity) -> Result<(), Self::Error>;
     async fn find_by_user(&self, user: &User) -> Result<Option<Identity>, Self::Error>;
+    // a user has a single identity, whichever role it is stored with
+    async fn find_by_user_id(&self, id: &Uuid) -> Result<Option<Identity>, Self::Error>;
+    // Moves the identity of the user to the role of the given user in one step, dropping its
+    // tokens, so that the user is never left without an identity
+    async fn change_role(&self, user: &User) -> Result<(), Self::Error>;
+    async fn exists_with_role(&self, role: &Role) -> Result<bool, Self::Error>;
 }
 
 #[derive(Default, Clone)]
@@ -61,6 +68,30 @@ impl Repository for MemoryRepository {
             None => Ok(None),
         }
     }
+
+    async fn find_by_user_id(&self, id: &Uuid) -> Result<Option<Identity>, Error> {
+        let store = self.rows.read().map_err(|_| Error::RwLockFailed)?;
+
+        Ok(store
+            .values()
+            .find(|identity| identity.user.id == *id)
+            .cloned())
+    }
+
+    async fn change_role(&self, user: &User) -> Result<(), Error> {
+        let mut store = self.rows.write().map_err(|_| Error::RwLockFailed)?;
+
+        store.retain(|_, identity| identity.user.id != user.id);
+        store.insert(self.get_key(user), Identity::new(user.clone(), None));
+
+        Ok(())
+    }
+
+    async fn exists_with_role(&self, role: &Role) -> Result<bool, Error> {
+        let store = self.rows.read().map_err(|_| Error::RwLockFailed)?;
+
+        Ok(store.values().any(|identity| identity.user.role == *role))
+    }
 }
 
 #[cfg(test)]
@@ -121,4 +152,40 @@ mod tests {
 
         assert!(result.is_none());
     }
+
+    #[tokio::test]
+    async fn repository_changes_role_of_identity_in_place() {
+        let repository = MemoryRepository::default();
+        let mut identity = Identity::new(User::new(Uuid::new_v4(), Role::Member), None);
+        identity.issue_tokens().await.unwrap();
+        repository.save(identity.clone()).await.unwrap();
+
+        let changed_user = User::new(identity.user.id, Role::Administrator);
+        repository.change_role(&changed_user).await.unwrap();
+
+        assert_eq!(
+            repository.find_by_user_id(&identity.user.id).await.unwrap(),
+            Some(Identity::new(changed_user, None))
+        );
+        assert_eq!(repository.rows.read().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn repository_finds_whether_any_identity_has_role() {
+        let repository = MemoryRepository::default();
+        let user = User {
+            id: Uuid::new_v4(),
+            role: Role::Member,
+        };
+        repository
+            .save(Identity::new(user.clone(), None))
+            .await
+            .unwrap();
+
+        assert!(repository.exists_with_role(&Role::Member).await.unwrap());
+        assert!(!repository
+            .exists_with_role(&Role::Administrator)
+            .await
+            .unwrap());
+    }
 }
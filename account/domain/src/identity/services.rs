@@ -1,6 +1,7 @@
 use crate::identity::errors::Error;
-use crate::identity::models::entities::{AccessToken, Identity, RefreshToken, Tokens, User};
+use crate::identity::models::entities::{AccessToken, Identity, RefreshToken, Role, Tokens, User};
 use crate::identity::repositories::Repository;
+use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct Service<R: Repository> {
@@ -64,12 +65,13 @@ where
         Ok(identity.tokens.unwrap())
     }
 
-    pub async fn invalidate_tokens(&self, user: User) -> Result<(), Error> {
+    // The identity is found by the user alone, as the role carried by an access token may be stale
+    pub async fn invalidate_tokens(&self, id: Uuid) -> Result<(), Error> {
         let mut identity = self
             .repository
-            .find_by_user(&user)
+            .find_by_user_id(&id)
             .await?
-            .ok_or(Error::IdentityNotFound(user.id))?;
+            .ok_or(Error::IdentityNotFound(id))?;
 
         identity.invalidate_tokens()?;
         self.repository.save(identity.clone()).await?;
@@ -77,6 +79,30 @@ where
         Ok(())
     }
 
+    // Moves the identity to the new role, dropping its refresh token so that the next sign-in
+    // issues tokens carrying the new role. The identity is found by the user alone, as its
+    // stored role may lag behind the user aggregate. Changing to the role already held is a no-op.
+    pub async fn change_role(&self, id: Uuid, role: Role) -> Result<User, Error> {
+        let identity = self
+            .repository
+            .find_by_user_id(&id)
+            .await?
+            .ok_or(Error::IdentityNotFound(id))?;
+
+        if identity.user.role == role {
+            return Ok(identity.user);
+        }
+
+        let changed_user = User::new(id, role);
+        self.repository.change_role(&changed_user).await?;
+
+        Ok(changed_user)
+    }
+
+    pub async fn exists_with_role(&self, role: Role) -> Result<bool, Error> {
+        Ok(self.repository.exists_with_role(&role).await?)
+    }
+
     pub async fn verify_access_token(&self, access_token: &AccessToken) -> Result<User, Error> {
         Identity::verify_access_token(access_token).await
     }
@@ -210,9 +236,88 @@ mod tests {
         service.register_identity(user.clone()).await.unwrap();
         service.issue_tokens(user.clone()).await.unwrap();
 
-        service.invalidate_tokens(user.clone()).await.unwrap();
+        service.invalidate_tokens(user.id).await.unwrap();
 
         let identity = repository.find_by_user(&user).await.unwrap().unwrap();
         assert_eq!(identity.tokens, None);
     }
+
+    #[tokio::test]
+    async fn token_invalidation_finds_identity_whose_role_changed_after_issue() {
+        let repository = MemoryRepository::default();
+        let service = Service::new(repository.clone());
+
+        let user = User::new(Uuid::new_v4(), Role::Member);
+        service.register_identity(user.clone()).await.unwrap();
+        let changed_user = service
+            .change_role(user.id, Role::Administrator)
+            .await
+            .unwrap();
+        service.issue_tokens(changed_user.clone()).await.unwrap();
+
+        service.invalidate_tokens(user.id).await.unwrap();
+
+        let identity = repository
+            .find_by_user(&changed_user)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(identity.tokens, None);
+    }
+
+    #[tokio::test]
+    async fn role_change_moves_identity_and_drops_its_tokens() {
+        let repository = MemoryRepository::default();
+        let service = Service::new(repository.clone());
+
+        let user = User::new(Uuid::new_v4(), Role::Member);
+        service.register_identity(user.clone()).await.unwrap();
+        service.issue_tokens(user.clone()).await.unwrap();
+
+        let changed_user = service
+            .change_role(user.id, Role::Administrator)
+            .await
+            .unwrap();
+
+        assert_eq!(changed_user, User::new(user.id, Role::Administrator));
+        assert!(repository.find_by_user(&user).await.unwrap().is_none());
+        let identity = repository
+            .find_by_user(&changed_user)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(identity.tokens, None);
+    }
+
+    #[tokio::test]
+    async fn role_change_to_role_already_held_keeps_identity_as_is() {
+        let repository = MemoryRepository::default();
+        let service = Service::new(repository.clone());
+
+        let user = User::new(Uuid::new_v4(), Role::Administrator);
+        service.register_identity(user.clone()).await.unwrap();
+        let tokens = service.issue_tokens(user.clone()).await.unwrap();
+
+        let changed_user = service
+            .change_role(user.id, Role::Administrator)
+            .await
+            .unwrap();
+
+        assert_eq!(changed_user, user);
+        let identity = repository.find_by_user(&user).await.unwrap().unwrap();
+        assert_eq!(identity.tokens.unwrap().refresh_token, tokens.refresh_token);
+    }
+
+    #[tokio::test]
+    async fn role_change_fails_when_identity_not_found() {
+        let repository = MemoryRepository::default();
+        let service = Service::new(repository.clone());
+
+        let error = service
+            .change_role(Uuid::new_v4(), Role::Administrator)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, Error::IdentityNotFound(..)));
+    }
 }
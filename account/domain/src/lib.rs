@@ -1,2 +1,4 @@
 pub mod identity;
+pub mod mailer;
+pub mod secrets;
 pub mod user;
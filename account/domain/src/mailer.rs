@@ -0,0 +1,62 @@
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Mail {
+    pub fn new(to: impl Into<String>, subject: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            to: to.into(),
+            subject: subject.into(),
+            body: body.into(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Failed to build a mail to {to}: {source}")]
+    InvalidMail {
+        to: String,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    #[error("Failed to deliver a mail: {0}")]
+    DeliveryFailed(#[source] anyhow::Error),
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, mail: Mail) -> Result<(), Error>;
+}
+
+// Keeps sent mails in memory, so that tests can inspect them
+#[derive(Default, Clone)]
+pub struct MemoryMailer {
+    mails: Arc<RwLock<Vec<Mail>>>,
+}
+
+impl MemoryMailer {
+    pub fn sent(&self) -> Vec<Mail> {
+        self.mails.read().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, mail: Mail) -> Result<(), Error> {
+        self.mails
+            .write()
+            .map_err(|_| Error::DeliveryFailed(anyhow::anyhow!("Failed to get a lock")))?
+            .push(mail);
+
+        Ok(())
+    }
+}
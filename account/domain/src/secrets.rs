@@ -0,0 +1,65 @@
+use std::env;
+use std::sync::OnceLock;
+
+static SECRETS: OnceLock<Secrets> = OnceLock::new();
+
+// Keys signing the issued tokens, configured once on start so that no token can be forged
+// without them
+#[derive(Debug, Clone)]
+pub struct Secrets {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub verification_token: String,
+    pub email_change_token: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Failed to read secret {0} because it is not set or empty")]
+    SecretNotFound(String),
+
+    #[error("Failed to configure secrets because they were already configured")]
+    SecretsAlreadyConfigured,
+}
+
+impl Secrets {
+    pub fn from_env() -> Result<Self, Error> {
+        Ok(Self {
+            access_token: read_env("ACCOUNT_ACCESS_TOKEN_SECRET")?,
+            refresh_token: read_env("ACCOUNT_REFRESH_TOKEN_SECRET")?,
+            verification_token: read_env("ACCOUNT_VERIFICATION_TOKEN_SECRET")?,
+            email_change_token: read_env("ACCOUNT_EMAIL_CHANGE_TOKEN_SECRET")?,
+        })
+    }
+}
+
+pub fn configure(secrets: Secrets) -> Result<(), Error> {
+    SECRETS
+        .set(secrets)
+        .map_err(|_| Error::SecretsAlreadyConfigured)
+}
+
+#[cfg(not(test))]
+pub(crate) fn get() -> &'static Secrets {
+    SECRETS
+        .get()
+        .expect("secrets have to be configured before tokens are issued or verified")
+}
+
+// tests sign with fixed secrets rather than configuring their own
+#[cfg(test)]
+pub(crate) fn get() -> &'static Secrets {
+    SECRETS.get_or_init(|| Secrets {
+        access_token: String::from("access-token-secret"),
+        refresh_token: String::from("refresh-token-secret"),
+        verification_token: String::from("verification-token-secret"),
+        email_change_token: String::from("email-change-token-secret"),
+    })
+}
+
+fn read_env(name: &str) -> Result<String, Error> {
+    env::var(name)
+        .ok()
+        .filter(|secret| !secret.is_empty())
+        .ok_or(Error::SecretNotFound(name.to_string()))
+}
@@ -1,12 +1,18 @@
+use std::sync::Arc;
+
 use uuid::Uuid;
 
-// use event_sourcing::aggregate::EventSourced;
+use event_sourcing::aggregate::EventSourced;
 use event_sourcing::envelope::Envelope;
 use event_sourcing::repository::error::Error as RepositoryError;
-use event_sourcing::repository::interface::Repository;
+use event_sourcing::repository::interface::{Reservations, ReservingRepository};
 
+use crate::mailer::{Mail, Mailer};
 use crate::user::errors::Error;
-use crate::user::models::User;
+use crate::user::models::{email_key, Role, User, EMAIL_SCOPE};
+use crate::user::tokens::{
+    decode_email_change_token, decode_verification_token, parse_reset_token,
+};
 
 #[derive(Debug)]
 pub enum Command {
@@ -17,16 +23,62 @@ pub enum Command {
         email: String,
         language: String,
     },
+    WithdrawUser {
+        id: Uuid,
+        reason: String,
+    },
+    ChangePassword {
+        id: Uuid,
+        current_password: String,
+        new_password: String,
+    },
+    ChangeName {
+        id: Uuid,
+        name: String,
+    },
+    ChangeLanguage {
+        id: Uuid,
+        language: String,
+    },
+    IssueVerification {
+        id: Uuid,
+    },
+    VerifyEmail {
+        token: String,
+    },
+    RequestPasswordReset {
+        email: String,
+    },
+    ResetPassword {
+        token: String,
+        new_password: String,
+    },
+    RequestEmailChange {
+        id: Uuid,
+        email: String,
+    },
+    ConfirmEmailChange {
+        token: String,
+    },
+    GrantRole {
+        id: Uuid,
+        role: String,
+    },
+    RevokeRole {
+        id: Uuid,
+        role: String,
+    },
 }
 
 #[derive(Clone)]
-pub struct CommandExecutor<R: Repository<User>> {
+pub struct CommandExecutor<R: ReservingRepository<User>> {
     repository: R,
+    mailer: Arc<dyn Mailer>,
 }
 
-impl<R: Repository<User>> CommandExecutor<R> {
-    pub fn new(repository: R) -> Self {
-        Self { repository }
+impl<R: ReservingRepository<User>> CommandExecutor<R> {
+    pub fn new(repository: R, mailer: Arc<dyn Mailer>) -> Self {
+        Self { repository, mailer }
     }
 
     async fn find_events(&self, id: &Uuid) -> Result<Vec<Envelope<User>>, Error> {
@@ -39,16 +91,32 @@ impl<R: Repository<User>> CommandExecutor<R> {
             })
     }
 
-    // async fn load_aggregate(&self, id: &Uuid) -> Result<User, Error> {
-    //     let events = self.find_events(&id).await?;
-    //     Ok(User::load(events).await)
-    // }
+    async fn load_aggregate(&self, id: &Uuid) -> Result<User, Error> {
+        let events = self.find_events(id).await?;
+        Ok(User::load(events).await)
+    }
 
     async fn save_aggregate(&mut self, aggregate: &mut User) -> Result<(), Error> {
+        self.save_aggregate_with(aggregate, Reservations::default())
+            .await
+    }
+
+    async fn save_aggregate_with(
+        &mut self,
+        aggregate: &mut User,
+        reservations: Reservations,
+    ) -> Result<(), Error> {
+        if aggregate.get_pending_events().is_empty() {
+            return Ok(());
+        }
+
         self.repository
-            .save(aggregate)
+            .save_with_reservations(aggregate, &reservations)
             .await
-            .map_err(|error| Error::DatabaseOperationFailed(error.into()))
+            .map_err(|error| match error {
+                RepositoryError::Reserved { key, .. } => Error::EmailAlreadyRegistered(key),
+                _ => Error::DatabaseOperationFailed(error.into()),
+            })
     }
 
     pub async fn execute(&mut self, command: Command) -> Result<(), Error> {
@@ -66,30 +134,211 @@ impl<R: Repository<User>> CommandExecutor<R> {
                     Ok(_) => Err(Error::UserAlreadyRegistered(id)),
                     Err(error) => match error {
                         Error::UserNotFound(id) => {
+                            let reservations =
+                                Reservations::default().reserve(EMAIL_SCOPE, email_key(&email));
                             let mut user = User::default();
                             user.register(id, name, password, email, language).await?;
-                            self.save_aggregate(&mut user).await?;
+                            self.save_aggregate_with(&mut user, reservations).await?;
                             Ok(())
                         }
                         _ => Err(Error::DatabaseOperationFailed(error.into())),
                     },
                 }
             }
+            Command::WithdrawUser { id, reason } => {
+                let mut user = self.load_aggregate(&id).await?;
+                user.withdraw(reason).await?;
+                let reservations =
+                    Reservations::default().release(EMAIL_SCOPE, email_key(&user.email));
+                self.save_aggregate_with(&mut user, reservations).await?;
+                Ok(())
+            }
+            Command::ChangePassword {
+                id,
+                current_password,
+                new_password,
+            } => {
+                let mut user = self.load_aggregate(&id).await?;
+                user.change_password(&current_password, &new_password)
+                    .await?;
+                self.save_aggregate(&mut user).await?;
+                Ok(())
+            }
+            Command::ChangeName { id, name } => {
+                let mut user = self.load_aggregate(&id).await?;
+                user.change_name(name).await?;
+                self.save_aggregate(&mut user).await?;
+                Ok(())
+            }
+            Command::ChangeLanguage { id, language } => {
+                let mut user = self.load_aggregate(&id).await?;
+                user.change_language(language).await?;
+                self.save_aggregate(&mut user).await?;
+                Ok(())
+            }
+            Command::IssueVerification { id } => {
+                let mut user = self.load_aggregate(&id).await?;
+                let token = user.issue_verification().await?;
+                self.save_aggregate(&mut user).await?;
+
+                let mail = Mail::new(
+                    &user.email,
+                    "Verify your email",
+                    format!(
+                        "Hello {},\n\nUse the following token to verify your email:\n\n{}\n",
+                        user.name, token
+                    ),
+                );
+                self.mailer.send(mail).await?;
+                Ok(())
+            }
+            Command::VerifyEmail { token } => {
+                let claims = decode_verification_token(&token)?;
+                let mut user =
+                    self.load_aggregate(&claims.id)
+                        .await
+                        .map_err(|error| match error {
+                            Error::UserNotFound(..) => Error::InvalidVerificationToken,
+                            _ => error,
+                        })?;
+                user.verify_email(claims.nonce).await?;
+                self.save_aggregate(&mut user).await?;
+                Ok(())
+            }
+            Command::RequestPasswordReset { email } => {
+                let id = self
+                    .repository
+                    .find_reservation(EMAIL_SCOPE, &email_key(&email))
+                    .await
+                    .map_err(|error| Error::DatabaseOperationFailed(error.into()))?
+                    .ok_or(Error::EmailNotFound(email))?;
+                let mut user = self.load_aggregate(&id).await?;
+                let token = user.request_password_reset().await?;
+                self.save_aggregate(&mut user).await?;
+
+                let mail = Mail::new(
+                    &user.email,
+                    "Reset your password",
+                    format!(
+                        "Hello {},\n\nUse the following token within 15 minutes to reset your password:\n\n{}\n",
+                        user.name, token
+                    ),
+                );
+                self.mailer.send(mail).await?;
+                Ok(())
+            }
+            Command::ResetPassword {
+                token,
+                new_password,
+            } => {
+                let (id, token_hash) = parse_reset_token(&token)?;
+                let mut user = self
+                    .load_aggregate(&id)
+                    .await
+                    .map_err(|error| match error {
+                        Error::UserNotFound(..) => Error::InvalidResetToken,
+                        _ => error,
+                    })?;
+                user.reset_password(&token_hash, &new_password).await?;
+                self.save_aggregate(&mut user).await?;
+                Ok(())
+            }
+            Command::RequestEmailChange { id, email } => {
+                let mut user = self.load_aggregate(&id).await?;
+                let token = user.request_email_change(email).await?;
+
+                // fail early, the reservation on confirmation still guards against races
+                let Some(pending_email) = &user.pending_email else {
+                    return Err(Error::Unexpected(anyhow::anyhow!(
+                        "Failed to find the requested email of user {}",
+                        id
+                    )));
+                };
+                let email = pending_email.email.clone();
+                let holder = self
+                    .repository
+                    .find_reservation(EMAIL_SCOPE, &email_key(&email))
+                    .await
+                    .map_err(|error| Error::DatabaseOperationFailed(error.into()))?;
+                if holder.is_some() {
+                    return Err(Error::EmailAlreadyRegistered(email));
+                }
+                self.save_aggregate(&mut user).await?;
+
+                let mail = Mail::new(
+                    &email,
+                    "Confirm your new email",
+                    format!(
+                        "Hello {},\n\nUse the following token to confirm {} as your new email:\n\n{}\n",
+                        user.name, email, token
+                    ),
+                );
+                self.mailer.send(mail).await?;
+                Ok(())
+            }
+            Command::ConfirmEmailChange { token } => {
+                let claims = decode_email_change_token(&token)?;
+                let mut user =
+                    self.load_aggregate(&claims.id)
+                        .await
+                        .map_err(|error| match error {
+                            Error::UserNotFound(..) => Error::InvalidEmailChangeToken,
+                            _ => error,
+                        })?;
+                let previous_email = user.email.clone();
+                user.confirm_email_change(claims.nonce).await?;
+                let reservations = Reservations::default()
+                    .release(EMAIL_SCOPE, email_key(&previous_email))
+                    .reserve(EMAIL_SCOPE, email_key(&user.email));
+                self.save_aggregate_with(&mut user, reservations).await?;
+
+                let mail = Mail::new(
+                    &previous_email,
+                    "Your email has been changed",
+                    format!(
+                        "Hello {},\n\nThe email of your account has been changed to {}.\nIf you did not request this change, please contact us.\n",
+                        user.name, user.email
+                    ),
+                );
+                self.mailer.send(mail).await?;
+                Ok(())
+            }
+            Command::GrantRole { id, role } => {
+                let role = Role::try_from(role.as_str())?;
+                let mut user = self.load_aggregate(&id).await?;
+                user.grant_role(role).await?;
+                self.save_aggregate(&mut user).await?;
+                Ok(())
+            }
+            Command::RevokeRole { id, role } => {
+                let role = Role::try_from(role.as_str())?;
+                let mut user = self.load_aggregate(&id).await?;
+                user.revoke_role(role).await?;
+                self.save_aggregate(&mut user).await?;
+                Ok(())
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use event_sourcing::aggregate::EventSourced;
+    use event_sourcing::repository::interface::Repository;
     use event_sourcing::repository::memory::MemoryRepository;
 
+    use crate::mailer::MemoryMailer;
     use crate::user::commands::*;
+    use crate::user::models::{Role, Status};
+
+    fn extract_token(mail: &Mail) -> String {
+        mail.body.trim_end().lines().last().unwrap().to_string()
+    }
 
     #[tokio::test]
     async fn new_user_registration_succeeds() {
         let repository = MemoryRepository::default();
-        let mut command_executor = CommandExecutor::new(repository.clone());
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
 
         let id = Uuid::new_v4();
         let command = Command::RegisterUser {
@@ -117,7 +366,8 @@ mod tests {
     #[tokio::test]
     async fn user_registration_fails_if_already_registered() {
         let repository = MemoryRepository::default();
-        let mut command_executor = CommandExecutor::new(repository);
+        let mut command_executor =
+            CommandExecutor::new(repository, Arc::new(MemoryMailer::default()));
 
         let id = Uuid::new_v4();
         let command = Command::RegisterUser {
@@ -140,4 +390,772 @@ mod tests {
 
         assert!(matches!(error, Error::UserAlreadyRegistered(..)));
     }
+
+    #[tokio::test]
+    async fn user_withdrawal_withdraws_registered_user() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let command = Command::WithdrawUser {
+            id,
+            reason: String::from("Moving to another service"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let envelopes: Vec<Envelope<User>> = repository.find_all_events(&id).await.unwrap();
+        assert_eq!(envelopes.len(), 2);
+
+        let user = User::load(envelopes).await;
+        assert_eq!(user.status, Status::Withdrawn);
+        assert!(user.is_withdrawn());
+    }
+
+    #[tokio::test]
+    async fn user_withdrawal_fails_if_already_withdrawn() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository, Arc::new(MemoryMailer::default()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let command = Command::WithdrawUser {
+            id,
+            reason: String::new(),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let command = Command::WithdrawUser {
+            id,
+            reason: String::new(),
+        };
+        let error = command_executor.execute(command).await.err().unwrap();
+
+        assert!(matches!(error, Error::UserAlreadyWithdrawn(..)));
+    }
+
+    #[tokio::test]
+    async fn user_withdrawal_fails_if_not_registered() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository, Arc::new(MemoryMailer::default()));
+
+        let command = Command::WithdrawUser {
+            id: Uuid::new_v4(),
+            reason: String::new(),
+        };
+        let error = command_executor.execute(command).await.err().unwrap();
+
+        assert!(matches!(error, Error::UserNotFound(..)));
+    }
+
+    #[tokio::test]
+    async fn password_change_replaces_password() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let command = Command::ChangePassword {
+            id,
+            current_password: String::from("welcome"),
+            new_password: String::from("farewell"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let envelopes: Vec<Envelope<User>> = repository.find_all_events(&id).await.unwrap();
+        assert_eq!(envelopes.len(), 2);
+
+        let user = User::load(envelopes).await;
+        assert!(user.verify_password("farewell"));
+        assert!(!user.verify_password("welcome"));
+    }
+
+    #[tokio::test]
+    async fn password_change_fails_if_current_password_is_wrong() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let command = Command::ChangePassword {
+            id,
+            current_password: String::from("wrong"),
+            new_password: String::from("farewell"),
+        };
+        let error = command_executor.execute(command).await.err().unwrap();
+
+        assert!(matches!(error, Error::InvalidCredential(..)));
+        let envelopes: Vec<Envelope<User>> = repository.find_all_events(&id).await.unwrap();
+        assert_eq!(envelopes.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn password_change_fails_if_new_password_is_too_short() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let command = Command::ChangePassword {
+            id,
+            current_password: String::from("welcome"),
+            new_password: String::from("short"),
+        };
+        let error = command_executor.execute(command).await.err().unwrap();
+
+        assert!(matches!(error, Error::InvalidPassword));
+        let envelopes: Vec<Envelope<User>> = repository.find_all_events(&id).await.unwrap();
+        assert_eq!(envelopes.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn profile_changes_update_name_and_language() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let command = Command::ChangeName {
+            id,
+            name: String::from("Ailee"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let command = Command::ChangeLanguage {
+            id,
+            language: String::from("ko-KR"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let envelopes: Vec<Envelope<User>> = repository.find_all_events(&id).await.unwrap();
+        assert_eq!(envelopes.len(), 3);
+
+        let user = User::load(envelopes).await;
+        assert_eq!(user.name, "Ailee");
+        assert_eq!(user.language, "ko-KR");
+    }
+
+    #[tokio::test]
+    async fn profile_changes_with_unchanged_values_emit_nothing() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let command = Command::ChangeName {
+            id,
+            name: String::from(" Arine "),
+        };
+        command_executor.execute(command).await.unwrap();
+        let command = Command::ChangeLanguage {
+            id,
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let envelopes: Vec<Envelope<User>> = repository.find_all_events(&id).await.unwrap();
+        assert_eq!(envelopes.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn profile_changes_fail_with_invalid_values() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository, Arc::new(MemoryMailer::default()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let command = Command::ChangeName {
+            id,
+            name: String::from("   "),
+        };
+        let error = command_executor.execute(command).await.err().unwrap();
+        assert!(matches!(error, Error::InvalidName(..)));
+
+        let command = Command::ChangeLanguage {
+            id,
+            language: String::from("English"),
+        };
+        let error = command_executor.execute(command).await.err().unwrap();
+        assert!(matches!(error, Error::InvalidLanguage(..)));
+    }
+
+    #[tokio::test]
+    async fn registered_user_is_activated_by_verifying_email() {
+        let repository = MemoryRepository::default();
+        let mailer = MemoryMailer::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(mailer.clone()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let user = User::load(repository.find_all_events(&id).await.unwrap()).await;
+        assert_eq!(user.status, Status::Registered);
+
+        command_executor
+            .execute(Command::IssueVerification { id })
+            .await
+            .unwrap();
+        let mails = mailer.sent();
+        assert_eq!(mails.len(), 1);
+        assert_eq!(mails[0].to, "peppydays@gmail.com");
+
+        let command = Command::VerifyEmail {
+            token: extract_token(&mails[0]),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let user = User::load(repository.find_all_events(&id).await.unwrap()).await;
+        assert_eq!(user.status, Status::Active);
+    }
+
+    #[tokio::test]
+    async fn verification_token_is_single_use() {
+        let repository = MemoryRepository::default();
+        let mailer = MemoryMailer::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(mailer.clone()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+        command_executor
+            .execute(Command::IssueVerification { id })
+            .await
+            .unwrap();
+        let token = extract_token(&mailer.sent()[0]);
+        command_executor
+            .execute(Command::VerifyEmail {
+                token: token.clone(),
+            })
+            .await
+            .unwrap();
+
+        let error = command_executor
+            .execute(Command::VerifyEmail { token })
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, Error::EmailAlreadyVerified(..)));
+    }
+
+    #[tokio::test]
+    async fn verification_fails_with_tampered_token() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository, Arc::new(MemoryMailer::default()));
+
+        let command = Command::VerifyEmail {
+            token: String::from("000.000.000"),
+        };
+        let error = command_executor.execute(command).await.unwrap_err();
+
+        assert!(matches!(error, Error::InvalidVerificationToken));
+    }
+
+    #[tokio::test]
+    async fn verification_issue_is_throttled() {
+        let repository = MemoryRepository::default();
+        let mailer = MemoryMailer::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(mailer.clone()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+        command_executor
+            .execute(Command::IssueVerification { id })
+            .await
+            .unwrap();
+
+        let error = command_executor
+            .execute(Command::IssueVerification { id })
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, Error::VerificationThrottled(..)));
+        assert_eq!(mailer.sent().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn password_is_reset_once_with_the_mailed_token() {
+        let repository = MemoryRepository::default();
+        let mailer = MemoryMailer::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(mailer.clone()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+        command_executor
+            .execute(Command::RequestPasswordReset {
+                email: String::from("peppydays@gmail.com"),
+            })
+            .await
+            .unwrap();
+        let token = extract_token(&mailer.sent()[0]);
+
+        let command = Command::ResetPassword {
+            token: token.clone(),
+            new_password: String::from("farewell"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let command = Command::ResetPassword {
+            token,
+            new_password: String::from("again"),
+        };
+        let error = command_executor.execute(command).await.unwrap_err();
+
+        assert!(matches!(error, Error::InvalidResetToken));
+        let user = User::load(repository.find_all_events(&id).await.unwrap()).await;
+        assert!(user.verify_password("farewell"));
+    }
+
+    #[tokio::test]
+    async fn password_reset_request_fails_for_unknown_email() {
+        let repository = MemoryRepository::default();
+        let mailer = MemoryMailer::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(mailer.clone()));
+
+        let command = Command::RequestPasswordReset {
+            email: String::from("peppydays@gmail.com"),
+        };
+        let error = command_executor.execute(command).await.unwrap_err();
+
+        assert!(matches!(error, Error::EmailNotFound(..)));
+        assert!(mailer.sent().is_empty());
+    }
+
+    #[tokio::test]
+    async fn password_reset_fails_with_wrong_secret() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+        command_executor
+            .execute(Command::RequestPasswordReset {
+                email: String::from("peppydays@gmail.com"),
+            })
+            .await
+            .unwrap();
+
+        let command = Command::ResetPassword {
+            token: format!("{}.guessed", id),
+            new_password: String::from("farewell"),
+        };
+        let error = command_executor.execute(command).await.unwrap_err();
+
+        assert!(matches!(error, Error::InvalidResetToken));
+    }
+
+    #[tokio::test]
+    async fn user_registration_fails_if_email_is_already_registered() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
+
+        let command = Command::RegisterUser {
+            id: Uuid::new_v4(),
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Ailee"),
+            password: String::from("welcome"),
+            email: String::from(" PeppyDays@gmail.com"),
+            language: String::from("en"),
+        };
+        let error = command_executor.execute(command).await.unwrap_err();
+
+        assert!(matches!(error, Error::EmailAlreadyRegistered(..)));
+        let result: Result<Vec<Envelope<User>>, RepositoryError> =
+            repository.find_all_events(&id).await;
+        assert!(matches!(result, Err(RepositoryError::NotFound(..))));
+    }
+
+    #[tokio::test]
+    async fn email_of_withdrawn_user_can_be_registered_again() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository, Arc::new(MemoryMailer::default()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let command = Command::WithdrawUser {
+            id,
+            reason: String::new(),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let command = Command::RegisterUser {
+            id: Uuid::new_v4(),
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+
+        assert!(command_executor.execute(command).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn email_is_changed_once_with_the_mailed_token() {
+        let repository = MemoryRepository::default();
+        let mailer = MemoryMailer::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(mailer.clone()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let command = Command::RequestEmailChange {
+            id,
+            email: String::from("arine@gmail.com"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let request = mailer.sent()[0].clone();
+        let token = extract_token(&request);
+
+        command_executor
+            .execute(Command::ConfirmEmailChange {
+                token: token.clone(),
+            })
+            .await
+            .unwrap();
+        let error = command_executor
+            .execute(Command::ConfirmEmailChange { token })
+            .await
+            .unwrap_err();
+
+        assert_eq!(request.to, "arine@gmail.com");
+        assert_eq!(mailer.sent()[1].to, "peppydays@gmail.com");
+        assert!(matches!(error, Error::InvalidEmailChangeToken));
+        let user = User::load(repository.find_all_events(&id).await.unwrap()).await;
+        assert_eq!(user.email, "arine@gmail.com");
+        assert_eq!(user.status, Status::Active);
+        assert_eq!(user.pending_email, None);
+        assert_eq!(
+            ReservingRepository::<User>::find_reservation(
+                &repository,
+                EMAIL_SCOPE,
+                "arine@gmail.com"
+            )
+            .await
+            .unwrap(),
+            Some(id)
+        );
+        assert_eq!(
+            ReservingRepository::<User>::find_reservation(
+                &repository,
+                EMAIL_SCOPE,
+                "peppydays@gmail.com"
+            )
+            .await
+            .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn email_change_fails_if_email_is_already_registered() {
+        let repository = MemoryRepository::default();
+        let mailer = MemoryMailer::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(mailer.clone()));
+
+        let command = Command::RegisterUser {
+            id: Uuid::new_v4(),
+            name: String::from("Ailee"),
+            password: String::from("welcome"),
+            email: String::from("ailee@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let command = Command::RequestEmailChange {
+            id,
+            email: String::from("Ailee@gmail.com"),
+        };
+        let error = command_executor.execute(command).await.unwrap_err();
+
+        assert!(matches!(error, Error::EmailAlreadyRegistered(..)));
+        assert!(mailer.sent().is_empty());
+    }
+
+    #[tokio::test]
+    async fn email_change_confirmation_fails_if_email_is_taken_meanwhile() {
+        let repository = MemoryRepository::default();
+        let mailer = MemoryMailer::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(mailer.clone()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let command = Command::RequestEmailChange {
+            id,
+            email: String::from("arine@gmail.com"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let token = extract_token(&mailer.sent()[0]);
+        let command = Command::RegisterUser {
+            id: Uuid::new_v4(),
+            name: String::from("Ailee"),
+            password: String::from("welcome"),
+            email: String::from("arine@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let error = command_executor
+            .execute(Command::ConfirmEmailChange { token })
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, Error::EmailAlreadyRegistered(..)));
+        let user = User::load(repository.find_all_events(&id).await.unwrap()).await;
+        assert_eq!(user.email, "peppydays@gmail.com");
+    }
+
+    #[tokio::test]
+    async fn email_change_fails_with_invalid_email() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository, Arc::new(MemoryMailer::default()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let command = Command::RequestEmailChange {
+            id,
+            email: String::from("arine"),
+        };
+        let error = command_executor.execute(command).await.unwrap_err();
+
+        assert!(matches!(error, Error::InvalidEmail(..)));
+    }
+
+    #[tokio::test]
+    async fn granted_role_is_revoked_back_to_member() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let command = Command::GrantRole {
+            id,
+            role: String::from("Administrator"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let user = User::load(repository.find_all_events(&id).await.unwrap()).await;
+        assert_eq!(user.role, Role::Administrator);
+
+        let command = Command::RevokeRole {
+            id,
+            role: String::from("Administrator"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let user = User::load(repository.find_all_events(&id).await.unwrap()).await;
+        assert_eq!(user.role, Role::Member);
+    }
+
+    #[tokio::test]
+    async fn role_changes_fail_if_they_change_nothing() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let command = Command::GrantRole {
+            id,
+            role: String::from("Member"),
+        };
+        let error = command_executor.execute(command).await.unwrap_err();
+        assert!(matches!(error, Error::RoleAlreadyGranted(..)));
+
+        let command = Command::RevokeRole {
+            id,
+            role: String::from("Administrator"),
+        };
+        let error = command_executor.execute(command).await.unwrap_err();
+        assert!(matches!(error, Error::RoleNotGranted(..)));
+
+        let command = Command::RevokeRole {
+            id,
+            role: String::from("Member"),
+        };
+        let error = command_executor.execute(command).await.unwrap_err();
+        assert!(matches!(error, Error::RoleNotRevocable(..)));
+
+        let command = Command::GrantRole {
+            id,
+            role: String::from("Owner"),
+        };
+        let error = command_executor.execute(command).await.unwrap_err();
+        assert!(matches!(error, Error::InvalidRole(..)));
+    }
 }
@@ -5,15 +5,66 @@ pub enum Error {
     #[error("Failed to register user {0} because it was already registered")]
     UserAlreadyRegistered(Uuid),
 
+    #[error("Failed to register email {0} because it was already registered")]
+    EmailAlreadyRegistered(String),
+
     #[error("Failed to withdraw user {0} because it was already withdrawn")]
     UserAlreadyWithdrawn(Uuid),
 
+    #[error("Failed to parse {0:?} as a role")]
+    InvalidRole(String),
+
+    #[error("Failed to grant role because user {0} already has role {1}")]
+    RoleAlreadyGranted(Uuid, String),
+
+    #[error("Failed to revoke role because user {0} does not have role {1}")]
+    RoleNotGranted(Uuid, String),
+
+    #[error("Failed to revoke role {0} because every user holds it")]
+    RoleNotRevocable(String),
+
     #[error("Failed to find user {0}")]
     UserNotFound(Uuid),
 
+    #[error("Failed to change name because {0:?} is not a valid name")]
+    InvalidName(String),
+
+    #[error("Failed to change language because {0:?} is not a valid language tag")]
+    InvalidLanguage(String),
+
+    #[error("Failed to find user with email {0}")]
+    EmailNotFound(String),
+
+    #[error("Failed to set password because it is not 8 to 128 characters long")]
+    InvalidPassword,
+
     #[error("Failed to verify credential of user {0}")]
     InvalidCredential(Uuid),
 
+    #[error("Failed to verify email of user {0} because it was already verified")]
+    EmailAlreadyVerified(Uuid),
+
+    #[error("Failed to verify email due to the invalid or used token")]
+    InvalidVerificationToken,
+
+    #[error("Failed to change email due to the invalid or used token")]
+    InvalidEmailChangeToken,
+
+    #[error("Failed to change email because {0:?} is not a valid email")]
+    InvalidEmail(String),
+
+    #[error("Failed to reset password due to the invalid, expired or used token")]
+    InvalidResetToken,
+
+    #[error("Failed to issue a verification token for user {0} because one was issued recently")]
+    VerificationThrottled(Uuid),
+
+    #[error("Failed to create a token: {0}")]
+    TokenCreationFailed(#[source] jsonwebtoken::errors::Error),
+
+    #[error("Failed to send a mail: {0}")]
+    MailDeliveryFailed(#[from] crate::mailer::Error),
+
     #[error("Failed to hashing a password: {0}")]
     HashingPasswordFailed(#[from] argon2::password_hash::Error),
 
@@ -1,13 +1,18 @@
-use crate::user::models::{Status, User};
+use std::time::SystemTime;
+
+use crate::user::models::{PasswordReset, PendingEmail, Status, User, Verification};
 use async_trait::async_trait;
 use event_sourcing::event::DomainEvent;
 use event_sourcing::event::EventApplier;
+use event_sourcing::repository::error::Error;
+use event_sourcing::schema::SchemaRegistry;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::user::models::Role;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum Event {
     UserRegistered {
         id: Uuid,
@@ -16,21 +21,152 @@ pub enum Event {
         email: String,
         language: String,
     },
+    UserWithdrawn {
+        reason: String,
+        withdrawn_at: SystemTime,
+    },
+    PasswordChanged {
+        password: String,
+        changed_at: SystemTime,
+    },
+    NameChanged {
+        name: String,
+    },
+    LanguageChanged {
+        language: String,
+    },
+    VerificationIssued {
+        nonce: Uuid,
+        issued_at: SystemTime,
+    },
+    EmailVerified {
+        email: String,
+        verified_at: SystemTime,
+    },
+    PasswordResetRequested {
+        token_hash: String,
+        expires_at: SystemTime,
+    },
+    PasswordReset {
+        password: String,
+        reset_at: SystemTime,
+    },
+    EmailChangeRequested {
+        email: String,
+        nonce: Uuid,
+        requested_at: SystemTime,
+    },
+    EmailChanged {
+        previous_email: String,
+        email: String,
+        changed_at: SystemTime,
+    },
+    RoleGranted {
+        role: Role,
+        granted_at: SystemTime,
+    },
+    RoleRevoked {
+        role: Role,
+        revoked_at: SystemTime,
+    },
 }
 
 impl DomainEvent for Event {
     fn get_name(&self) -> String {
         match self {
             Event::UserRegistered { .. } => String::from("UserRegistered"),
+            Event::UserWithdrawn { .. } => String::from("UserWithdrawn"),
+            Event::PasswordChanged { .. } => String::from("PasswordChanged"),
+            Event::NameChanged { .. } => String::from("NameChanged"),
+            Event::LanguageChanged { .. } => String::from("LanguageChanged"),
+            Event::VerificationIssued { .. } => String::from("VerificationIssued"),
+            Event::EmailVerified { .. } => String::from("EmailVerified"),
+            Event::PasswordResetRequested { .. } => String::from("PasswordResetRequested"),
+            Event::PasswordReset { .. } => String::from("PasswordReset"),
+            Event::EmailChangeRequested { .. } => String::from("EmailChangeRequested"),
+            Event::EmailChanged { .. } => String::from("EmailChanged"),
+            Event::RoleGranted { .. } => String::from("RoleGranted"),
+            Event::RoleRevoked { .. } => String::from("RoleRevoked"),
         }
     }
     fn get_version(&self) -> String {
         match self {
             Event::UserRegistered { .. } => String::from("1.0.0"),
+            Event::UserWithdrawn { .. } => String::from("1.0.0"),
+            Event::PasswordChanged { .. } => String::from("1.0.0"),
+            Event::NameChanged { .. } => String::from("1.0.0"),
+            Event::LanguageChanged { .. } => String::from("1.0.0"),
+            Event::VerificationIssued { .. } => String::from("1.0.0"),
+            Event::EmailVerified { .. } => String::from("1.0.0"),
+            Event::PasswordResetRequested { .. } => String::from("1.0.0"),
+            Event::PasswordReset { .. } => String::from("1.0.0"),
+            Event::EmailChangeRequested { .. } => String::from("1.0.0"),
+            Event::EmailChanged { .. } => String::from("1.0.0"),
+            Event::RoleGranted { .. } => String::from("1.0.0"),
+            Event::RoleRevoked { .. } => String::from("1.0.0"),
         }
     }
 }
 
+// Registers a sample of every event, so the catalogue covers all of them
+pub fn register_schemas(registry: &mut SchemaRegistry) -> Result<(), Error> {
+    registry.register::<User>(&Event::UserRegistered {
+        id: Uuid::nil(),
+        name: String::new(),
+        password: String::new(),
+        email: String::new(),
+        language: String::new(),
+    })?;
+    registry.register::<User>(&Event::UserWithdrawn {
+        reason: String::new(),
+        withdrawn_at: SystemTime::UNIX_EPOCH,
+    })?;
+    registry.register::<User>(&Event::PasswordChanged {
+        password: String::new(),
+        changed_at: SystemTime::UNIX_EPOCH,
+    })?;
+    registry.register::<User>(&Event::NameChanged {
+        name: String::new(),
+    })?;
+    registry.register::<User>(&Event::LanguageChanged {
+        language: String::new(),
+    })?;
+    registry.register::<User>(&Event::VerificationIssued {
+        nonce: Uuid::nil(),
+        issued_at: SystemTime::UNIX_EPOCH,
+    })?;
+    registry.register::<User>(&Event::EmailVerified {
+        email: String::new(),
+        verified_at: SystemTime::UNIX_EPOCH,
+    })?;
+    registry.register::<User>(&Event::PasswordResetRequested {
+        token_hash: String::new(),
+        expires_at: SystemTime::UNIX_EPOCH,
+    })?;
+    registry.register::<User>(&Event::PasswordReset {
+        password: String::new(),
+        reset_at: SystemTime::UNIX_EPOCH,
+    })?;
+    registry.register::<User>(&Event::EmailChangeRequested {
+        email: String::new(),
+        nonce: Uuid::nil(),
+        requested_at: SystemTime::UNIX_EPOCH,
+    })?;
+    registry.register::<User>(&Event::EmailChanged {
+        previous_email: String::new(),
+        email: String::new(),
+        changed_at: SystemTime::UNIX_EPOCH,
+    })?;
+    registry.register::<User>(&Event::RoleGranted {
+        role: Role::Administrator,
+        granted_at: SystemTime::UNIX_EPOCH,
+    })?;
+    registry.register::<User>(&Event::RoleRevoked {
+        role: Role::Administrator,
+        revoked_at: SystemTime::UNIX_EPOCH,
+    })
+}
+
 #[async_trait]
 impl EventApplier<User> for User {
     async fn apply(&mut self, event: Event) {
@@ -48,8 +184,106 @@ impl EventApplier<User> for User {
                 self.email = email;
                 self.language = language;
                 self.role = Role::Member;
+                self.status = Status::Registered;
+            }
+            Event::UserWithdrawn { .. } => {
+                self.status = Status::Withdrawn;
+            }
+            Event::PasswordChanged { password, .. } => {
+                self.password = password;
+                self.password_reset = None;
+            }
+            Event::NameChanged { name } => {
+                self.name = name;
+            }
+            Event::LanguageChanged { language } => {
+                self.language = language;
+            }
+            Event::VerificationIssued { nonce, issued_at } => {
+                self.verification = Some(Verification { nonce, issued_at });
+            }
+            Event::EmailVerified { .. } => {
                 self.status = Status::Active;
+                self.verification = None;
+            }
+            Event::PasswordResetRequested {
+                token_hash,
+                expires_at,
+            } => {
+                self.password_reset = Some(PasswordReset {
+                    token_hash,
+                    expires_at,
+                });
+            }
+            Event::PasswordReset { password, .. } => {
+                self.password = password;
+                self.password_reset = None;
+            }
+            Event::EmailChangeRequested {
+                email,
+                nonce,
+                requested_at,
+            } => {
+                self.pending_email = Some(PendingEmail {
+                    email,
+                    nonce,
+                    requested_at,
+                });
+            }
+            // confirming the new address verifies it as well
+            Event::EmailChanged { email, .. } => {
+                self.email = email;
+                self.pending_email = None;
+                self.verification = None;
+                self.status = Status::Active;
+            }
+            Event::RoleGranted { role, .. } => {
+                self.role = role;
+            }
+            Event::RoleRevoked { .. } => {
+                self.role = Role::Member;
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use event_sourcing::schema::SchemaRegistry;
+    use serde_json::json;
+
+    use crate::user::events::register_schemas;
+
+    #[test]
+    fn user_registered_is_validated_against_its_schema() {
+        let mut registry = SchemaRegistry::default();
+        register_schemas(&mut registry).unwrap();
+
+        let valid = registry.validate(
+            "User",
+            "UserRegistered",
+            "1.0.0",
+            &json!({ "UserRegistered": {
+                "id": "5f8c3d4e-0b7a-4a1e-9c2d-3e4f5a6b7c8d",
+                "name": "Arine",
+                "password": "hashed",
+                "email": "arine@example.com",
+                "language": "en",
+            }}),
+        );
+        let missing_email = registry.validate(
+            "User",
+            "UserRegistered",
+            "1.0.0",
+            &json!({ "UserRegistered": {
+                "id": "5f8c3d4e-0b7a-4a1e-9c2d-3e4f5a6b7c8d",
+                "name": "Arine",
+                "password": "hashed",
+                "language": "en",
+            }}),
+        );
+
+        assert!(valid.is_ok());
+        assert!(missing_email.is_err());
+    }
+}
@@ -3,3 +3,8 @@ pub mod errors;
 mod events;
 mod models;
 pub mod queries;
+mod tokens;
+
+pub use events::register_schemas;
+pub use models::User;
+pub use tokens::parse_reset_token;
@@ -4,12 +4,25 @@ use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use async_trait::async_trait;
 use event_sourcing::aggregate::EventSourced;
 use event_sourcing::envelope::Envelope;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
+use std::ops::RangeInclusive;
+use std::time::{Duration, SystemTime};
 use uuid::Uuid;
 
 use crate::user::errors::Error;
 use crate::user::events::Event;
+use crate::user::tokens::{issue_email_change_token, issue_reset_token, issue_verification_token};
+
+const MAX_NAME_LENGTH: usize = 100;
+// the upper bound keeps hashing cheap, as passwords are hashed before anything else
+const PASSWORD_LENGTH: RangeInclusive<usize> = 8..=128;
+const VERIFICATION_RESEND_INTERVAL: Duration = Duration::from_secs(60);
+const PASSWORD_RESET_DURATION: Duration = Duration::from_secs(15 * 60);
+// Emails are reserved in this scope, so that no two users are registered with the same one.
+// The reservations also serve as the lookup of users by email.
+pub(crate) const EMAIL_SCOPE: &str = "email";
 
 #[derive(Default, Serialize, Deserialize, Debug, PartialEq)]
 pub struct User {
@@ -20,17 +33,39 @@ pub struct User {
     pub language: String,
     pub role: Role,
     pub status: Status,
+    pub verification: Option<Verification>,
+    pub password_reset: Option<PasswordReset>,
+    pub pending_email: Option<PendingEmail>,
     sequence: i64,
     pending_events: Vec<Envelope<Self>>,
 }
 
-#[derive(Default, Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
 pub enum Role {
     #[default]
     Member,
     Administrator,
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Verification {
+    pub nonce: Uuid,
+    pub issued_at: SystemTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PasswordReset {
+    pub token_hash: String,
+    pub expires_at: SystemTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PendingEmail {
+    pub email: String,
+    pub nonce: Uuid,
+    pub requested_at: SystemTime,
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize, Default)]
 pub enum Status {
     #[default]
@@ -39,6 +74,27 @@ pub enum Status {
     Withdrawn,
 }
 
+impl Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::Member => write!(f, "Member"),
+            Role::Administrator => write!(f, "Administrator"),
+        }
+    }
+}
+
+impl TryFrom<&str> for Role {
+    type Error = Error;
+
+    fn try_from(role: &str) -> Result<Self, Self::Error> {
+        match role {
+            "Member" => Ok(Role::Member),
+            "Administrator" => Ok(Role::Administrator),
+            _ => Err(Error::InvalidRole(role.to_string())),
+        }
+    }
+}
+
 impl Display for Status {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -98,10 +154,255 @@ impl User {
         Ok(())
     }
 
+    pub async fn withdraw(&mut self, reason: String) -> Result<(), Error> {
+        if self.is_withdrawn() {
+            return Err(Error::UserAlreadyWithdrawn(self.id));
+        }
+
+        let event = Event::UserWithdrawn {
+            reason,
+            withdrawn_at: SystemTime::now(),
+        };
+        self.update(event).await;
+
+        Ok(())
+    }
+
+    // A user holds a single role, so granting one replaces the current role
+    pub async fn grant_role(&mut self, role: Role) -> Result<(), Error> {
+        if self.is_withdrawn() {
+            return Err(Error::UserAlreadyWithdrawn(self.id));
+        }
+        if self.role == role {
+            return Err(Error::RoleAlreadyGranted(self.id, role.to_string()));
+        }
+
+        let event = Event::RoleGranted {
+            role,
+            granted_at: SystemTime::now(),
+        };
+        self.update(event).await;
+
+        Ok(())
+    }
+
+    // Revoking a role returns the user to a member, which itself cannot be revoked
+    pub async fn revoke_role(&mut self, role: Role) -> Result<(), Error> {
+        if self.is_withdrawn() {
+            return Err(Error::UserAlreadyWithdrawn(self.id));
+        }
+        if role == Role::Member {
+            return Err(Error::RoleNotRevocable(role.to_string()));
+        }
+        if self.role != role {
+            return Err(Error::RoleNotGranted(self.id, role.to_string()));
+        }
+
+        let event = Event::RoleRevoked {
+            role,
+            revoked_at: SystemTime::now(),
+        };
+        self.update(event).await;
+
+        Ok(())
+    }
+
+    pub async fn change_password(
+        &mut self,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), Error> {
+        if self.is_withdrawn() {
+            return Err(Error::UserAlreadyWithdrawn(self.id));
+        }
+        if !self.verify_password(current_password) {
+            return Err(Error::InvalidCredential(self.id));
+        }
+        if !User::is_valid_password(new_password) {
+            return Err(Error::InvalidPassword);
+        }
+
+        let event = Event::PasswordChanged {
+            password: User::hash_password(new_password)?,
+            changed_at: SystemTime::now(),
+        };
+        self.update(event).await;
+
+        Ok(())
+    }
+
+    // Unchanged values are accepted without emitting an event
+    pub async fn change_name(&mut self, name: String) -> Result<(), Error> {
+        if self.is_withdrawn() {
+            return Err(Error::UserAlreadyWithdrawn(self.id));
+        }
+
+        let name = name.trim().to_string();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err(Error::InvalidName(name));
+        }
+        if name == self.name {
+            return Ok(());
+        }
+
+        self.update(Event::NameChanged { name }).await;
+
+        Ok(())
+    }
+
+    pub async fn change_language(&mut self, language: String) -> Result<(), Error> {
+        if self.is_withdrawn() {
+            return Err(Error::UserAlreadyWithdrawn(self.id));
+        }
+
+        if !User::is_valid_language(&language) {
+            return Err(Error::InvalidLanguage(language));
+        }
+        if language == self.language {
+            return Ok(());
+        }
+
+        self.update(Event::LanguageChanged { language }).await;
+
+        Ok(())
+    }
+
+    // Returns a signed token whose nonce is the only one accepted until the next issue
+    pub async fn issue_verification(&mut self) -> Result<String, Error> {
+        if self.is_withdrawn() {
+            return Err(Error::UserAlreadyWithdrawn(self.id));
+        }
+        if self.status == Status::Active {
+            return Err(Error::EmailAlreadyVerified(self.id));
+        }
+        if let Some(verification) = &self.verification {
+            let elapsed = verification.issued_at.elapsed().unwrap_or_default();
+            if elapsed < VERIFICATION_RESEND_INTERVAL {
+                return Err(Error::VerificationThrottled(self.id));
+            }
+        }
+
+        let nonce = Uuid::new_v4();
+        let issued_at = SystemTime::now();
+        let token = issue_verification_token(self.id, nonce, issued_at)?;
+        self.update(Event::VerificationIssued { nonce, issued_at })
+            .await;
+
+        Ok(token)
+    }
+
+    pub async fn verify_email(&mut self, nonce: Uuid) -> Result<(), Error> {
+        if self.is_withdrawn() {
+            return Err(Error::UserAlreadyWithdrawn(self.id));
+        }
+        if self.status == Status::Active {
+            return Err(Error::EmailAlreadyVerified(self.id));
+        }
+        if self.verification.as_ref().map(|v| v.nonce) != Some(nonce) {
+            return Err(Error::InvalidVerificationToken);
+        }
+
+        let event = Event::EmailVerified {
+            email: self.email.clone(),
+            verified_at: SystemTime::now(),
+        };
+        self.update(event).await;
+
+        Ok(())
+    }
+
+    pub async fn request_password_reset(&mut self) -> Result<String, Error> {
+        if self.is_withdrawn() {
+            return Err(Error::UserAlreadyWithdrawn(self.id));
+        }
+
+        let (token, token_hash) = issue_reset_token(self.id);
+        let event = Event::PasswordResetRequested {
+            token_hash,
+            expires_at: SystemTime::now() + PASSWORD_RESET_DURATION,
+        };
+        self.update(event).await;
+
+        Ok(token)
+    }
+
+    pub async fn reset_password(&mut self, token_hash: &str, password: &str) -> Result<(), Error> {
+        if self.is_withdrawn() {
+            return Err(Error::UserAlreadyWithdrawn(self.id));
+        }
+        match &self.password_reset {
+            Some(reset)
+                if reset.token_hash == token_hash && reset.expires_at > SystemTime::now() => {}
+            _ => return Err(Error::InvalidResetToken),
+        }
+        if !User::is_valid_password(password) {
+            return Err(Error::InvalidPassword);
+        }
+
+        let event = Event::PasswordReset {
+            password: User::hash_password(password)?,
+            reset_at: SystemTime::now(),
+        };
+        self.update(event).await;
+
+        Ok(())
+    }
+
+    // Returns a signed token for the new address, replacing any earlier request
+    pub async fn request_email_change(&mut self, email: String) -> Result<String, Error> {
+        if self.is_withdrawn() {
+            return Err(Error::UserAlreadyWithdrawn(self.id));
+        }
+
+        let email = email.trim().to_string();
+        if !User::is_valid_email(&email) {
+            return Err(Error::InvalidEmail(email));
+        }
+        if email_key(&email) == email_key(&self.email) {
+            return Err(Error::EmailAlreadyRegistered(email));
+        }
+
+        let nonce = Uuid::new_v4();
+        let requested_at = SystemTime::now();
+        let token = issue_email_change_token(self.id, nonce, requested_at)?;
+        let event = Event::EmailChangeRequested {
+            email,
+            nonce,
+            requested_at,
+        };
+        self.update(event).await;
+
+        Ok(token)
+    }
+
+    pub async fn confirm_email_change(&mut self, nonce: Uuid) -> Result<(), Error> {
+        if self.is_withdrawn() {
+            return Err(Error::UserAlreadyWithdrawn(self.id));
+        }
+        let email = match &self.pending_email {
+            Some(pending) if pending.nonce == nonce => pending.email.clone(),
+            _ => return Err(Error::InvalidEmailChangeToken),
+        };
+
+        let event = Event::EmailChanged {
+            previous_email: self.email.clone(),
+            email,
+            changed_at: SystemTime::now(),
+        };
+        self.update(event).await;
+
+        Ok(())
+    }
+
     pub fn is_withdrawn(&self) -> bool {
         self.status == Status::Withdrawn
     }
 
+    // verified and not withdrawn
+    pub fn is_active(&self) -> bool {
+        self.status == Status::Active
+    }
+
     pub fn verify_password(&self, password: &str) -> bool {
         Argon2::default()
             .verify_password(
@@ -111,6 +412,38 @@ impl User {
             .is_ok()
     }
 
+    fn is_valid_password(password: &str) -> bool {
+        PASSWORD_LENGTH.contains(&password.chars().count())
+    }
+
+    // Accepts a primary language subtag with an optional region, e.g. "en" or "pt-BR"
+    fn is_valid_language(language: &str) -> bool {
+        let mut subtags = language.split('-');
+        let primary = subtags.next().unwrap_or_default();
+        let region = subtags.next();
+
+        (2..=3).contains(&primary.len())
+            && primary.chars().all(|c| c.is_ascii_lowercase())
+            && region.is_none_or(|region| {
+                region.len() == 2 && region.chars().all(|c| c.is_ascii_uppercase())
+            })
+            && subtags.next().is_none()
+    }
+
+    // a local part and a domain with at least one dot, leaving the rest to the confirmation
+    fn is_valid_email(email: &str) -> bool {
+        match email.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty()
+                    && !domain.contains('@')
+                    && domain.split('.').count() > 1
+                    && domain.split('.').all(|label| !label.is_empty())
+                    && !email.chars().any(char::is_whitespace)
+            }
+            None => false,
+        }
+    }
+
     fn hash_password(password: &str) -> Result<String, Error> {
         let hashed_password = Argon2::default()
             .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))?
@@ -119,3 +452,68 @@ impl User {
         Ok(hashed_password)
     }
 }
+
+// emails differing only in case or surrounding spaces are the same address
+pub(crate) fn email_key(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::user::models::*;
+
+    #[test]
+    fn language_tags_are_validated() {
+        assert!(User::is_valid_language("en"));
+        assert!(User::is_valid_language("ko-KR"));
+        assert!(User::is_valid_language("fil"));
+        assert!(!User::is_valid_language(""));
+        assert!(!User::is_valid_language("EN"));
+        assert!(!User::is_valid_language("en-"));
+        assert!(!User::is_valid_language("en-us"));
+        assert!(!User::is_valid_language("english"));
+        assert!(!User::is_valid_language("zh-Hant-TW"));
+    }
+
+    #[test]
+    fn emails_are_validated() {
+        assert!(User::is_valid_email("peppydays@gmail.com"));
+        assert!(User::is_valid_email("a.b+c@mail.example.co.kr"));
+        assert!(!User::is_valid_email("peppydays"));
+        assert!(!User::is_valid_email("@gmail.com"));
+        assert!(!User::is_valid_email("peppydays@localhost"));
+        assert!(!User::is_valid_email("peppy days@gmail.com"));
+        assert!(!User::is_valid_email("a@b@gmail.com"));
+        assert!(!User::is_valid_email("peppydays@gmail..com"));
+    }
+
+    #[test]
+    fn passwords_are_validated() {
+        assert!(User::is_valid_password("farewell"));
+        assert!(User::is_valid_password(&"p".repeat(128)));
+        assert!(!User::is_valid_password("welcome"));
+        assert!(!User::is_valid_password(&"p".repeat(129)));
+    }
+
+    #[tokio::test]
+    async fn expired_password_reset_is_rejected() {
+        let mut user = User::default();
+        user.register(
+            Uuid::new_v4(),
+            String::from("Arine"),
+            String::from("welcome"),
+            String::from("peppydays@gmail.com"),
+            String::from("en"),
+        )
+        .await
+        .unwrap();
+        user.password_reset = Some(PasswordReset {
+            token_hash: String::from("hash"),
+            expires_at: SystemTime::now() - Duration::from_secs(1),
+        });
+
+        let error = user.reset_password("hash", "farewell").await.unwrap_err();
+
+        assert!(matches!(error, Error::InvalidResetToken));
+    }
+}
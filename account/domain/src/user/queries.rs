@@ -3,23 +3,24 @@ use uuid::Uuid;
 use event_sourcing::aggregate::EventSourced;
 use event_sourcing::envelope::Envelope;
 use event_sourcing::repository::error::Error as RepositoryError;
-use event_sourcing::repository::interface::Repository;
+use event_sourcing::repository::interface::ReservingRepository;
 
 use crate::user::errors::Error;
-use crate::user::models::User;
+use crate::user::models::{email_key, User, EMAIL_SCOPE};
 
 #[derive(Debug)]
 pub enum Query {
     GetUser { id: Uuid },
-    VerifyCredential { id: Uuid, password: String },
+    GetUserByEmail { email: String },
+    VerifyCredential { email: String, password: String },
 }
 
 #[derive(Clone)]
-pub struct QueryReader<R: Repository<User>> {
+pub struct QueryReader<R: ReservingRepository<User>> {
     repository: R,
 }
 
-impl<R: Repository<User>> QueryReader<R> {
+impl<R: ReservingRepository<User>> QueryReader<R> {
     pub fn new(repository: R) -> Self {
         Self { repository }
     }
@@ -48,6 +49,14 @@ impl<R: Repository<User>> QueryReader<R> {
         Ok(User::load(events).await)
     }
 
+    async fn find_id_by_email(&self, email: &str) -> Result<Uuid, Error> {
+        self.repository
+            .find_reservation(EMAIL_SCOPE, &email_key(email))
+            .await
+            .map_err(|error| Error::DatabaseOperationFailed(error.into()))?
+            .ok_or(Error::EmailNotFound(email.to_string()))
+    }
+
     pub async fn read(&self, query: Query) -> Result<User, Error> {
         match query {
             Query::GetUser { id } => {
@@ -58,9 +67,18 @@ impl<R: Repository<User>> QueryReader<R> {
                     false => Ok(user),
                 }
             }
-            Query::VerifyCredential { id, password } => {
+            Query::GetUserByEmail { email } => {
+                let id = self.find_id_by_email(&email).await?;
+                self.load_aggregate(&id).await
+            }
+            Query::VerifyCredential { email, password } => {
+                let id = self.find_id_by_email(&email).await?;
                 let user = self.load_aggregate(&id).await?;
 
+                if user.is_withdrawn() {
+                    return Err(Error::UserAlreadyWithdrawn(user.id));
+                }
+
                 match user.verify_password(&password) {
                     true => Ok(user),
                     false => Err(Error::InvalidCredential(user.id)),
@@ -72,14 +90,18 @@ impl<R: Repository<User>> QueryReader<R> {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use event_sourcing::repository::memory::MemoryRepository;
 
+    use crate::mailer::MemoryMailer;
     use crate::user::{commands::*, queries::*};
 
     #[tokio::test]
     async fn verifying_with_correct_password_returns_aggregate_instance() {
         let repository = MemoryRepository::default();
-        let mut command_executor = CommandExecutor::new(repository.clone());
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
         let query_reader = QueryReader::new(repository.clone());
 
         let id = Uuid::new_v4();
@@ -93,7 +115,7 @@ mod tests {
         command_executor.execute(command).await.unwrap();
 
         let query = Query::VerifyCredential {
-            id,
+            email: String::from("peppydays@gmail.com"),
             password: String::from("welcome"),
         };
 
@@ -103,7 +125,8 @@ mod tests {
     #[tokio::test]
     async fn verifying_with_incorrect_password_returns_error() {
         let repository = MemoryRepository::default();
-        let mut command_executor = CommandExecutor::new(repository.clone());
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
         let query_reader = QueryReader::new(repository.clone());
 
         let id = Uuid::new_v4();
@@ -117,7 +140,7 @@ mod tests {
         command_executor.execute(command).await.unwrap();
 
         let query = Query::VerifyCredential {
-            id,
+            email: String::from("peppydays@gmail.com"),
             password: String::from("thanks"),
         };
 
@@ -126,4 +149,112 @@ mod tests {
             Error::InvalidCredential(..)
         ));
     }
+
+    #[tokio::test]
+    async fn verifying_credential_of_withdrawn_user_returns_error() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
+        let query_reader = QueryReader::new(repository.clone());
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let command = Command::WithdrawUser {
+            id,
+            reason: String::new(),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let query = Query::VerifyCredential {
+            email: String::from("peppydays@gmail.com"),
+            password: String::from("welcome"),
+        };
+
+        // the withdrawal released the email, so the user is no longer found by it
+        assert!(matches!(
+            query_reader.read(query).await.unwrap_err(),
+            Error::EmailNotFound(..)
+        ));
+    }
+
+    #[tokio::test]
+    async fn getting_user_reflects_profile_changes() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
+        let query_reader = QueryReader::new(repository.clone());
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let command = Command::ChangeName {
+            id,
+            name: String::from("Ailee"),
+        };
+        command_executor.execute(command).await.unwrap();
+        let command = Command::ChangeLanguage {
+            id,
+            language: String::from("ko"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let user = query_reader.read(Query::GetUser { id }).await.unwrap();
+
+        assert_eq!(user.name, "Ailee");
+        assert_eq!(user.language, "ko");
+    }
+
+    #[tokio::test]
+    async fn verifying_with_unknown_email_returns_error() {
+        let repository = MemoryRepository::default();
+        let query_reader = QueryReader::new(repository);
+
+        let query = Query::VerifyCredential {
+            email: String::from("nobody@example.com"),
+            password: String::from("welcome"),
+        };
+
+        assert!(matches!(
+            query_reader.read(query).await.unwrap_err(),
+            Error::EmailNotFound(..)
+        ));
+    }
+
+    #[tokio::test]
+    async fn getting_user_by_email_ignores_case_and_whitespace() {
+        let repository = MemoryRepository::default();
+        let mut command_executor =
+            CommandExecutor::new(repository.clone(), Arc::new(MemoryMailer::default()));
+        let query_reader = QueryReader::new(repository.clone());
+
+        let id = Uuid::new_v4();
+        let command = Command::RegisterUser {
+            id,
+            name: String::from("Arine"),
+            password: String::from("welcome"),
+            email: String::from("peppydays@gmail.com"),
+            language: String::from("en"),
+        };
+        command_executor.execute(command).await.unwrap();
+
+        let query = Query::GetUserByEmail {
+            email: String::from(" PeppyDays@gmail.com "),
+        };
+        let user = query_reader.read(query).await.unwrap();
+
+        assert_eq!(user.id, id);
+    }
 }
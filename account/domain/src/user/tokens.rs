@@ -0,0 +1,162 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use jsonwebtoken::{
+    decode as jwt_decode, encode as jwt_encode, Algorithm as JwtAlgorithm,
+    DecodingKey as JwtDecodingKey, EncodingKey as JwtEncodingKey, Header as JwtHeader,
+    Validation as JwtValidation,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::secrets;
+use crate::user::errors::Error;
+
+const NONCE_TOKEN_DURATION_IN_HOURS: u64 = 24;
+
+// Claims of signed tokens carrying a nonce, which the aggregate accepts only once
+#[derive(Serialize, Deserialize)]
+pub struct NonceTokenClaims {
+    pub iat: u64,
+    pub exp: u64,
+    pub id: Uuid,
+    pub nonce: Uuid,
+}
+
+pub fn issue_verification_token(
+    id: Uuid,
+    nonce: Uuid,
+    issued_at: SystemTime,
+) -> Result<String, Error> {
+    issue_nonce_token(&secrets::get().verification_token, id, nonce, issued_at)
+}
+
+pub fn decode_verification_token(token: &str) -> Result<NonceTokenClaims, Error> {
+    decode_nonce_token(&secrets::get().verification_token, token)
+        .ok_or(Error::InvalidVerificationToken)
+}
+
+pub fn issue_email_change_token(
+    id: Uuid,
+    nonce: Uuid,
+    issued_at: SystemTime,
+) -> Result<String, Error> {
+    issue_nonce_token(&secrets::get().email_change_token, id, nonce, issued_at)
+}
+
+pub fn decode_email_change_token(token: &str) -> Result<NonceTokenClaims, Error> {
+    decode_nonce_token(&secrets::get().email_change_token, token)
+        .ok_or(Error::InvalidEmailChangeToken)
+}
+
+fn issue_nonce_token(
+    secret: &str,
+    id: Uuid,
+    nonce: Uuid,
+    issued_at: SystemTime,
+) -> Result<String, Error> {
+    let iat = issued_at.duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let claims = NonceTokenClaims {
+        iat,
+        exp: iat + (NONCE_TOKEN_DURATION_IN_HOURS * 60 * 60),
+        id,
+        nonce,
+    };
+    let key = JwtEncodingKey::from_secret(secret.as_ref());
+
+    jwt_encode(&JwtHeader::new(JwtAlgorithm::HS256), &claims, &key)
+        .map_err(Error::TokenCreationFailed)
+}
+
+fn decode_nonce_token(secret: &str, token: &str) -> Option<NonceTokenClaims> {
+    jwt_decode::<NonceTokenClaims>(
+        token,
+        &JwtDecodingKey::from_secret(secret.as_ref()),
+        &JwtValidation::new(JwtAlgorithm::HS256),
+    )
+    .map(|token| token.claims)
+    .ok()
+}
+
+// A reset token is the user id and a random secret, of which only the hash is stored
+pub fn issue_reset_token(id: Uuid) -> (String, String) {
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    let secret = hex::encode(secret);
+
+    (format!("{}.{}", id, secret), hash_reset_secret(&secret))
+}
+
+pub fn parse_reset_token(token: &str) -> Result<(Uuid, String), Error> {
+    let (id, secret) = token.split_once('.').ok_or(Error::InvalidResetToken)?;
+    let id = Uuid::parse_str(id).map_err(|_| Error::InvalidResetToken)?;
+
+    Ok((id, hash_reset_secret(secret)))
+}
+
+fn hash_reset_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::user::tokens::*;
+
+    #[test]
+    fn verification_token_round_trips_its_claims() {
+        let id = Uuid::new_v4();
+        let nonce = Uuid::new_v4();
+
+        let token = issue_verification_token(id, nonce, SystemTime::now()).unwrap();
+        let claims = decode_verification_token(&token).unwrap();
+
+        assert_eq!(claims.id, id);
+        assert_eq!(claims.nonce, nonce);
+    }
+
+    #[test]
+    fn expired_verification_token_is_rejected() {
+        let token = issue_verification_token(Uuid::new_v4(), Uuid::new_v4(), UNIX_EPOCH).unwrap();
+
+        assert!(matches!(
+            decode_verification_token(&token),
+            Err(Error::InvalidVerificationToken)
+        ));
+    }
+
+    #[test]
+    fn tokens_are_not_accepted_for_another_purpose() {
+        let token =
+            issue_verification_token(Uuid::new_v4(), Uuid::new_v4(), SystemTime::now()).unwrap();
+
+        assert!(matches!(
+            decode_email_change_token(&token),
+            Err(Error::InvalidEmailChangeToken)
+        ));
+    }
+
+    #[test]
+    fn reset_token_is_parsed_into_id_and_stored_hash() {
+        let id = Uuid::new_v4();
+
+        let (token, hash) = issue_reset_token(id);
+        let (parsed_id, parsed_hash) = parse_reset_token(&token).unwrap();
+
+        assert_eq!(parsed_id, id);
+        assert_eq!(parsed_hash, hash);
+        assert!(!token.contains(&hash));
+    }
+
+    #[test]
+    fn malformed_reset_token_is_rejected() {
+        assert!(matches!(
+            parse_reset_token("not-a-token"),
+            Err(Error::InvalidResetToken)
+        ));
+        assert!(matches!(
+            parse_reset_token("not-a-uuid.secret"),
+            Err(Error::InvalidResetToken)
+        ));
+    }
+}
@@ -1 +1,2 @@
+pub mod mailers;
 pub mod repositories;
@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+
+use domain::mailer::{Error, Mail, Mailer};
+
+// Appends mails to a local file instead of delivering them, for development
+#[derive(Clone)]
+pub struct FileMailer {
+    path: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, mail: Mail) -> Result<(), Error> {
+        log::info!("Writing a mail to {} into {:?}", &mail.to, &self.path);
+
+        let content = format!(
+            "To: {}\nSubject: {}\n\n{}\n---\n",
+            mail.to, mail.subject, mail.body
+        );
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|error| Error::DeliveryFailed(error.into()))?;
+        file.write_all(content.as_bytes())
+            .await
+            .map_err(|error| Error::DeliveryFailed(error.into()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mailers::file::*;
+
+    #[tokio::test]
+    async fn mails_are_appended_to_the_file() {
+        let path = std::env::temp_dir().join(format!("mails-{}.log", std::process::id()));
+        let mailer = FileMailer::new(&path);
+
+        mailer
+            .send(Mail::new("arine@example.com", "First", "Hello"))
+            .await
+            .unwrap();
+        mailer
+            .send(Mail::new("ailee@example.com", "Second", "Bye"))
+            .await
+            .unwrap();
+
+        let content = tokio::fs::read_to_string(&path).await.unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        assert!(content.contains("To: arine@example.com\nSubject: First\n\nHello"));
+        assert!(content.contains("To: ailee@example.com\nSubject: Second\n\nBye"));
+    }
+}
@@ -0,0 +1,63 @@
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use domain::mailer::{Error, Mail, Mailer};
+
+#[derive(Clone)]
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(
+        host: &str,
+        port: u16,
+        username: String,
+        password: String,
+        from: &str,
+    ) -> Result<Self, Error> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+            .map_err(|error| Error::DeliveryFailed(error.into()))?
+            .port(port)
+            .credentials(Credentials::new(username, password))
+            .build();
+        let from = from
+            .parse()
+            .map_err(|error: lettre::address::AddressError| Error::InvalidMail {
+                to: from.to_string(),
+                source: error.into(),
+            })?;
+
+        Ok(Self { transport, from })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, mail: Mail) -> Result<(), Error> {
+        let invalid = |error: anyhow::Error| Error::InvalidMail {
+            to: mail.to.clone(),
+            source: error,
+        };
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(mail
+                .to
+                .parse()
+                .map_err(|error: lettre::address::AddressError| invalid(error.into()))?)
+            .subject(&mail.subject)
+            .body(mail.body.clone())
+            .map_err(|error| invalid(error.into()))?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|error| Error::DeliveryFailed(error.into()))?;
+
+        Ok(())
+    }
+}
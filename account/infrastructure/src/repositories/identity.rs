@@ -1,8 +1,9 @@
 use async_trait::async_trait;
 use sqlx::mysql::MySqlRow;
+use sqlx::types::Uuid;
 use sqlx::{query, MySql, Pool, Row};
 
-use domain::identity::models::entities::{Identity, Tokens, User};
+use domain::identity::models::entities::{Identity, Role, Tokens, User};
 use domain::identity::repositories::Repository;
 
 #[derive(Clone)]
@@ -36,8 +37,8 @@ impl Repository for MySqlRepository {
         query("INSERT INTO identities (user_id, user_role, refresh_token) VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE refresh_token = ?")
             .bind(identity.user.id)
             .bind(Into::<&str>::into(identity.user.role))
-            .bind(&identity.tokens.clone().map(|tokens| Into::<String>::into(tokens.refresh_token)))
-            .bind(&identity.tokens.clone().map(|tokens| Into::<String>::into(tokens.refresh_token)))
+            .bind(identity.tokens.clone().map(|tokens| Into::<String>::into(tokens.refresh_token)))
+            .bind(identity.tokens.clone().map(|tokens| Into::<String>::into(tokens.refresh_token)))
             .execute(&self.pool)
             .await
             .map_err(Error::QueryExecutionFailed)?;
@@ -62,4 +63,42 @@ impl Repository for MySqlRepository {
 
         Ok(identity)
     }
+
+    async fn find_by_user_id(&self, id: &Uuid) -> Result<Option<Identity>, Error> {
+        query("SELECT user_role, refresh_token FROM identities WHERE user_id = ?")
+            .bind(id)
+            .try_map(|row: MySqlRow| {
+                let role = Role::try_from(row.get::<&str, &str>("user_role"))
+                    .map_err(|error| sqlx::Error::Decode(error.into()))?;
+                Ok(Identity {
+                    user: User::new(*id, role),
+                    tokens: row
+                        .get::<Option<&str>, &str>("refresh_token")
+                        .map(|refresh_token| Tokens::new("".into(), refresh_token.into())),
+                })
+            })
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::QueryExecutionFailed)
+    }
+
+    async fn change_role(&self, user: &User) -> Result<(), Error> {
+        query("UPDATE identities SET user_role = ?, refresh_token = NULL WHERE user_id = ?")
+            .bind(Into::<&str>::into(user.role.clone()))
+            .bind(user.id)
+            .execute(&self.pool)
+            .await
+            .map_err(Error::QueryExecutionFailed)?;
+
+        Ok(())
+    }
+
+    async fn exists_with_role(&self, role: &Role) -> Result<bool, Error> {
+        query("SELECT COUNT(*) AS identities FROM identities WHERE user_role = ?")
+            .bind(Into::<&str>::into(role.clone()))
+            .map(|row: MySqlRow| row.get::<i64, &str>("identities") > 0)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(Error::QueryExecutionFailed)
+    }
 }
@@ -3,6 +3,7 @@ CREATE SCHEMA es;
 USE es;
 
 CREATE TABLE events (
+    tenant_id          VARBINARY(16) NOT NULL,
     id                 VARBINARY(16) NOT NULL,
     aggregate_name     VARCHAR(50)   NOT NULL,
     aggregate_id       VARBINARY(16) NOT NULL,
@@ -10,7 +11,73 @@ CREATE TABLE events (
     event_name         VARCHAR(50)   NOT NULL,
     event_version      VARCHAR(10)   NOT NULL,
     event_payload      JSON          NOT NULL,
-    metadata           JSON          NOT NULL
+    metadata           JSON          NOT NULL,
+    created_at         DATETIME(3)   NOT NULL DEFAULT CURRENT_TIMESTAMP(3)
 );
 
-CREATE UNIQUE INDEX ix01_events ON events (aggregate_name, aggregate_id, aggregate_sequence);
+CREATE UNIQUE INDEX ix01_events ON events (tenant_id, aggregate_name, aggregate_id, aggregate_sequence);
+CREATE INDEX ix02_events ON events (tenant_id, aggregate_name, event_name, event_version, created_at);
+CREATE INDEX ix03_events ON events (tenant_id, aggregate_name, created_at);
+
+-- events of archived streams, moved out of the live table
+CREATE TABLE archived_events (
+    tenant_id          VARBINARY(16) NOT NULL,
+    id                 VARBINARY(16) NOT NULL,
+    aggregate_name     VARCHAR(50)   NOT NULL,
+    aggregate_id       VARBINARY(16) NOT NULL,
+    aggregate_sequence BIGINT        NOT NULL,
+    event_name         VARCHAR(50)   NOT NULL,
+    event_version      VARCHAR(10)   NOT NULL,
+    event_payload      JSON          NOT NULL,
+    metadata           JSON          NOT NULL,
+    created_at         DATETIME(3)   NOT NULL DEFAULT CURRENT_TIMESTAMP(3)
+);
+
+CREATE UNIQUE INDEX ix01_archived_events ON archived_events (tenant_id, aggregate_name, aggregate_id, aggregate_sequence);
+
+-- tombstoned or archived streams
+CREATE TABLE stream_states (
+    tenant_id      VARBINARY(16) NOT NULL,
+    aggregate_name VARCHAR(50)   NOT NULL,
+    aggregate_id   VARBINARY(16) NOT NULL,
+    state          VARCHAR(10)   NOT NULL
+);
+
+CREATE UNIQUE INDEX ix01_stream_states ON stream_states (tenant_id, aggregate_name, aggregate_id);
+
+-- keys of idempotent saves, unique per aggregate type
+CREATE TABLE idempotency_keys (
+    tenant_id       VARBINARY(16) NOT NULL,
+    aggregate_name  VARCHAR(50)   NOT NULL,
+    idempotency_key VARCHAR(100)  NOT NULL,
+    aggregate_id    VARBINARY(16) NOT NULL,
+    created_at      DATETIME(3)   NOT NULL DEFAULT CURRENT_TIMESTAMP(3)
+);
+
+CREATE UNIQUE INDEX ix01_idempotency_keys ON idempotency_keys (tenant_id, aggregate_name, idempotency_key);
+CREATE INDEX ix02_idempotency_keys ON idempotency_keys (tenant_id, aggregate_name, aggregate_id);
+
+CREATE TABLE reserved_keys (
+    tenant_id      VARBINARY(16) NOT NULL,
+    aggregate_name VARCHAR(50)   NOT NULL,
+    scope          VARCHAR(50)   NOT NULL,
+    reserved_key   VARCHAR(255)  NOT NULL,
+    aggregate_id   VARBINARY(16) NOT NULL,
+    created_at     DATETIME(3)   NOT NULL DEFAULT CURRENT_TIMESTAMP(3)
+);
+
+CREATE UNIQUE INDEX ix01_reserved_keys ON reserved_keys (tenant_id, aggregate_name, scope, reserved_key);
+CREATE INDEX ix02_reserved_keys ON reserved_keys (tenant_id, aggregate_name, aggregate_id);
+
+-- messages scheduled to be delivered later, with times in milliseconds since the Unix epoch
+CREATE TABLE scheduled_messages (
+    id           VARBINARY(16) NOT NULL,
+    name         VARCHAR(50)   NOT NULL,
+    payload      JSON          NOT NULL,
+    due_at       BIGINT        NOT NULL,
+    locked_until BIGINT        NOT NULL DEFAULT 0,
+    attempts     INT           NOT NULL DEFAULT 0
+);
+
+CREATE UNIQUE INDEX ix01_scheduled_messages ON scheduled_messages (id);
+CREATE INDEX ix02_scheduled_messages ON scheduled_messages (due_at);
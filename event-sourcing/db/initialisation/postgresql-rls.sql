@@ -0,0 +1,22 @@
+-- Optional row-level security for repositories built with with_row_level_security, which set
+-- app.tenant_id locally in every transaction. Table owners and superusers bypass these
+-- policies, so the application should connect with a separate role. Migrations that replace
+-- the events table carry its policies over to the new one, but copy the events of every tenant
+-- and so have to run as a role that bypasses them.
+ALTER TABLE events ENABLE ROW LEVEL SECURITY;
+ALTER TABLE archived_events ENABLE ROW LEVEL SECURITY;
+ALTER TABLE stream_states ENABLE ROW LEVEL SECURITY;
+ALTER TABLE idempotency_keys ENABLE ROW LEVEL SECURITY;
+ALTER TABLE reserved_keys ENABLE ROW LEVEL SECURITY;
+
+-- connections without a tenant see no rows
+CREATE POLICY tenant_isolation ON events
+    USING (tenant_id = current_setting('app.tenant_id', true)::UUID);
+CREATE POLICY tenant_isolation ON archived_events
+    USING (tenant_id = current_setting('app.tenant_id', true)::UUID);
+CREATE POLICY tenant_isolation ON stream_states
+    USING (tenant_id = current_setting('app.tenant_id', true)::UUID);
+CREATE POLICY tenant_isolation ON idempotency_keys
+    USING (tenant_id = current_setting('app.tenant_id', true)::UUID);
+CREATE POLICY tenant_isolation ON reserved_keys
+    USING (tenant_id = current_setting('app.tenant_id', true)::UUID);
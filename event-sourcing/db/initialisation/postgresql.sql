@@ -1,11 +1,122 @@
+-- Partitioned by month of creation, which the primary key has to include, so repositories
+-- check sequences of a stream themselves. Monthly partitions are created ahead of time with
+-- create_event_partitions, and events outside of all of them land in events_default. Only the
+-- first year is created here, so PostgresRepository::create_partitions has to run regularly,
+-- e.g. once a month, to keep creating the months ahead.
 CREATE TABLE events (
+    position           BIGSERIAL   NOT NULL,
+    tenant_id          UUID        NOT NULL,
     id                 UUID        NOT NULL,
     aggregate_name     VARCHAR(50) NOT NULL,
     aggregate_id       UUID        NOT NULL,
     aggregate_sequence BIGINT      NOT NULL,
     event_name         VARCHAR(50) NOT NULL,
     event_version      VARCHAR(10) NOT NULL,
-    event_payload      JSON        NOT NULL,
-    metadata           JSON        NOT NULL,
-    PRIMARY KEY (aggregate_name, aggregate_id, aggregate_sequence)
+    event_payload      JSONB       NOT NULL,
+    metadata           JSONB       NOT NULL,
+    created_at         TIMESTAMPTZ NOT NULL DEFAULT now(),
+    transaction_id     XID8        NOT NULL DEFAULT pg_current_xact_id(),
+    PRIMARY KEY (tenant_id, aggregate_name, aggregate_id, aggregate_sequence, created_at)
+) PARTITION BY RANGE (created_at);
+
+CREATE UNIQUE INDEX ix01_events ON events (position, created_at);
+CREATE INDEX ix02_events ON events (tenant_id, aggregate_name, position);
+CREATE INDEX ix03_events ON events (tenant_id, aggregate_name, event_name, event_version, created_at);
+CREATE INDEX ix04_events ON events (tenant_id, aggregate_name, created_at);
+CREATE INDEX ix05_events ON events USING GIN (metadata jsonb_path_ops);
+
+CREATE TABLE events_default PARTITION OF events DEFAULT;
+
+-- Creates the monthly partitions of the table from the month of the given date on, named
+-- like events_2024_01. A month can no longer be created once events_default holds events of it.
+CREATE FUNCTION create_event_partitions(parent TEXT, first_month DATE, months INT) RETURNS VOID AS $$
+DECLARE
+    month_start DATE;
+BEGIN
+    FOR i IN 0 .. months - 1 LOOP
+        month_start := date_trunc('month', first_month) + make_interval(months => i);
+        EXECUTE format(
+            'CREATE TABLE IF NOT EXISTS %I PARTITION OF %I FOR VALUES FROM (%L) TO (%L)',
+            parent || to_char(month_start, '_YYYY_MM'),
+            parent,
+            month_start,
+            month_start + INTERVAL '1 month'
+        );
+    END LOOP;
+END;
+$$ LANGUAGE plpgsql;
+
+SELECT create_event_partitions('events', CURRENT_DATE, 12);
+
+-- events of archived streams, moved out of the live table with their global positions
+CREATE TABLE archived_events (
+    position           BIGINT      NOT NULL,
+    tenant_id          UUID        NOT NULL,
+    id                 UUID        NOT NULL,
+    aggregate_name     VARCHAR(50) NOT NULL,
+    aggregate_id       UUID        NOT NULL,
+    aggregate_sequence BIGINT      NOT NULL,
+    event_name         VARCHAR(50) NOT NULL,
+    event_version      VARCHAR(10) NOT NULL,
+    event_payload      JSONB       NOT NULL,
+    metadata           JSONB       NOT NULL,
+    created_at         TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (tenant_id, aggregate_name, aggregate_id, aggregate_sequence)
+);
+
+-- tombstoned or archived streams
+CREATE TABLE stream_states (
+    tenant_id      UUID        NOT NULL,
+    aggregate_name VARCHAR(50) NOT NULL,
+    aggregate_id   UUID        NOT NULL,
+    state          VARCHAR(10) NOT NULL,
+    PRIMARY KEY (tenant_id, aggregate_name, aggregate_id)
+);
+
+-- keys of idempotent saves, unique per aggregate type
+CREATE TABLE idempotency_keys (
+    tenant_id       UUID         NOT NULL,
+    aggregate_name  VARCHAR(50)  NOT NULL,
+    idempotency_key VARCHAR(100) NOT NULL,
+    aggregate_id    UUID         NOT NULL,
+    created_at      TIMESTAMPTZ  NOT NULL DEFAULT now(),
+    PRIMARY KEY (tenant_id, aggregate_name, idempotency_key)
+);
+
+CREATE INDEX ix01_idempotency_keys ON idempotency_keys (tenant_id, aggregate_name, aggregate_id);
+
+-- unique keys held by one aggregate at a time, per aggregate type and scope
+CREATE TABLE reserved_keys (
+    tenant_id      UUID         NOT NULL,
+    aggregate_name VARCHAR(50)  NOT NULL,
+    scope          VARCHAR(50)  NOT NULL,
+    reserved_key   VARCHAR(255) NOT NULL,
+    aggregate_id   UUID         NOT NULL,
+    created_at     TIMESTAMPTZ  NOT NULL DEFAULT now(),
+    PRIMARY KEY (tenant_id, aggregate_name, scope, reserved_key)
+);
+
+CREATE INDEX ix01_reserved_keys ON reserved_keys (tenant_id, aggregate_name, aggregate_id);
+
+-- notifies the global position of every new event to subscribers
+CREATE FUNCTION notify_events() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('events', NEW.position::TEXT);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tr01_events AFTER INSERT ON events FOR EACH ROW EXECUTE FUNCTION notify_events();
+
+-- messages scheduled to be delivered later, with times in milliseconds since the Unix epoch
+CREATE TABLE scheduled_messages (
+    id           UUID        NOT NULL,
+    name         VARCHAR(50) NOT NULL,
+    payload      JSON        NOT NULL,
+    due_at       BIGINT      NOT NULL,
+    locked_until BIGINT      NOT NULL DEFAULT 0,
+    attempts     INT         NOT NULL DEFAULT 0,
+    PRIMARY KEY (id)
 );
+
+CREATE INDEX ix01_scheduled_messages ON scheduled_messages (due_at);
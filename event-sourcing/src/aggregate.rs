@@ -49,6 +49,14 @@ pub trait EventSourced:
         }
         aggregate
     }
+
+    async fn load_many(streams: HashMap<Uuid, Vec<Envelope<Self>>>) -> HashMap<Uuid, Self> {
+        let mut aggregates = HashMap::with_capacity(streams.len());
+        for (aggregate_id, enveloped_events) in streams {
+            aggregates.insert(aggregate_id, Self::load(enveloped_events).await);
+        }
+        aggregates
+    }
 }
 
 #[cfg(test)]
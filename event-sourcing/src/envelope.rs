@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::time::SystemTime;
 
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -8,10 +9,13 @@ use crate::aggregate::EventSourced;
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct Envelope<A: EventSourced> {
     pub id: Uuid,
+    // set by the repository the envelope is saved to, nil for the default tenant
+    pub tenant_id: Uuid,
     pub aggregate_id: Uuid,
     pub aggregate_sequence: i64,
     pub event: A::Event,
     pub metadata: HashMap<String, String>,
+    pub created_at: SystemTime,
 }
 
 impl<A: EventSourced> Envelope<A> {
@@ -23,10 +27,12 @@ impl<A: EventSourced> Envelope<A> {
     ) -> Self {
         Self {
             id: Uuid::new_v4(),
+            tenant_id: Uuid::nil(),
             aggregate_id,
             aggregate_sequence,
             event,
             metadata,
+            created_at: SystemTime::now(),
         }
     }
 }
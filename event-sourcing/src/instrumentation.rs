@@ -0,0 +1,320 @@
+// Spans and metrics around repository operations. Everything here compiles down to
+// plain awaits unless the `instrumentation` feature is enabled.
+
+use std::collections::HashMap;
+use std::future::Future;
+
+use uuid::Uuid;
+
+use crate::aggregate::EventSourced;
+use crate::envelope::Envelope;
+use crate::repository::error::Error;
+
+#[cfg(feature = "instrumentation")]
+const OPERATION_DURATION_METRIC: &str = "event_sourcing_repository_operation_duration_seconds";
+#[cfg(feature = "instrumentation")]
+const OPERATION_ERRORS_METRIC: &str = "event_sourcing_repository_operation_errors_total";
+#[cfg(feature = "instrumentation")]
+const EVENTS_METRIC: &str = "event_sourcing_repository_events_total";
+
+#[cfg_attr(
+    not(feature = "instrumentation"),
+    allow(clippy::extra_unused_type_parameters)
+)]
+pub(crate) async fn save<A, T>(
+    backend: &'static str,
+    aggregate_id: Uuid,
+    sequence: i64,
+    event_count: usize,
+    operation: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error>
+where
+    A: EventSourced,
+{
+    #[cfg(feature = "instrumentation")]
+    {
+        let span = tracing::info_span!(
+            "event_sourcing.repository.save",
+            backend,
+            aggregate_name = %A::get_name(),
+            %aggregate_id,
+            sequence,
+            event_count,
+        );
+        observe::<A, _>("save", backend, event_count, span, operation).await
+    }
+
+    #[cfg(not(feature = "instrumentation"))]
+    {
+        let _ = (backend, aggregate_id, sequence, event_count);
+        operation.await
+    }
+}
+
+pub(crate) async fn find_all_events<A, F>(
+    backend: &'static str,
+    aggregate_id: &Uuid,
+    operation: F,
+) -> Result<Vec<Envelope<A>>, Error>
+where
+    A: EventSourced,
+    F: Future<Output = Result<Vec<Envelope<A>>, Error>>,
+{
+    #[cfg(feature = "instrumentation")]
+    {
+        let span = tracing::info_span!(
+            "event_sourcing.repository.find_all_events",
+            backend,
+            aggregate_name = %A::get_name(),
+            %aggregate_id,
+            event_count = tracing::field::Empty,
+            sequence = tracing::field::Empty,
+        );
+        let result = observe::<A, _>("find_all_events", backend, 0, span.clone(), operation).await;
+
+        if let Ok(envelopes) = &result {
+            span.record("event_count", envelopes.len());
+            if let Some(last) = envelopes.last() {
+                span.record("sequence", last.aggregate_sequence);
+            }
+            metrics::counter!(
+                EVENTS_METRIC,
+                "operation" => "find_all_events",
+                "backend" => backend,
+                "aggregate" => A::get_name(),
+            )
+            .increment(envelopes.len() as u64);
+        }
+
+        result
+    }
+
+    #[cfg(not(feature = "instrumentation"))]
+    {
+        let _ = (backend, aggregate_id);
+        operation.await
+    }
+}
+
+pub(crate) async fn find_many_events<A, F>(
+    backend: &'static str,
+    aggregate_count: usize,
+    operation: F,
+) -> Result<HashMap<Uuid, Vec<Envelope<A>>>, Error>
+where
+    A: EventSourced,
+    F: Future<Output = Result<HashMap<Uuid, Vec<Envelope<A>>>, Error>>,
+{
+    #[cfg(feature = "instrumentation")]
+    {
+        let span = tracing::info_span!(
+            "event_sourcing.repository.find_many_events",
+            backend,
+            aggregate_name = %A::get_name(),
+            aggregate_count,
+            found_count = tracing::field::Empty,
+            event_count = tracing::field::Empty,
+        );
+        let result = observe::<A, _>("find_many_events", backend, 0, span.clone(), operation).await;
+
+        if let Ok(streams) = &result {
+            let event_count = streams.values().map(Vec::len).sum::<usize>();
+            span.record("found_count", streams.len());
+            span.record("event_count", event_count);
+            metrics::counter!(
+                EVENTS_METRIC,
+                "operation" => "find_many_events",
+                "backend" => backend,
+                "aggregate" => A::get_name(),
+            )
+            .increment(event_count as u64);
+        }
+
+        result
+    }
+
+    #[cfg(not(feature = "instrumentation"))]
+    {
+        let _ = (backend, aggregate_count);
+        operation.await
+    }
+}
+
+#[cfg(feature = "instrumentation")]
+async fn observe<A, T>(
+    operation: &'static str,
+    backend: &'static str,
+    event_count: usize,
+    span: tracing::Span,
+    future: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error>
+where
+    A: EventSourced,
+{
+    use std::time::Instant;
+
+    use tracing::Instrument;
+
+    let started_at = Instant::now();
+    let result = future.instrument(span.clone()).await;
+
+    metrics::histogram!(
+        OPERATION_DURATION_METRIC,
+        "operation" => operation,
+        "backend" => backend,
+        "aggregate" => A::get_name(),
+    )
+    .record(started_at.elapsed().as_secs_f64());
+
+    match &result {
+        Ok(_) => {
+            if event_count > 0 {
+                metrics::counter!(
+                    EVENTS_METRIC,
+                    "operation" => operation,
+                    "backend" => backend,
+                    "aggregate" => A::get_name(),
+                )
+                .increment(event_count as u64);
+            }
+        }
+        Err(error) => {
+            span.in_scope(|| tracing::warn!(%error, "repository operation failed"));
+            metrics::counter!(
+                OPERATION_ERRORS_METRIC,
+                "operation" => operation,
+                "backend" => backend,
+                "aggregate" => A::get_name(),
+                "error" => error_label(error),
+            )
+            .increment(1);
+        }
+    }
+
+    result
+}
+
+#[cfg(feature = "instrumentation")]
+fn error_label(error: &Error) -> &'static str {
+    match error {
+        Error::Serialization(..) => "serialization",
+        Error::Deserialization(..) => "deserialization",
+        Error::Connection(..) => "connection",
+        Error::Transaction(..) => "transaction",
+        Error::Execution(..) => "execution",
+        Error::Commit(..) => "commit",
+        Error::Conflict(..) => "conflict",
+        Error::NotFound(..) => "not_found",
+        Error::Deleted(..) => "deleted",
+        Error::Archived(..) => "archived",
+        Error::Reserved { .. } => "reserved",
+        Error::Unknown => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::instrumentation;
+    use crate::repository::error::Error;
+    use crate::test::*;
+
+    #[tokio::test]
+    async fn instrumented_save_passes_through_operation_result() {
+        let id = Uuid::new_v4();
+
+        let succeeded = instrumentation::save::<User, _>("test", id, 1, 1, async { Ok(()) }).await;
+        let failed =
+            instrumentation::save::<User, ()>("test", id, 1, 1, async { Err(Error::Unknown) })
+                .await;
+
+        assert!(succeeded.is_ok());
+        assert!(matches!(failed, Err(Error::Unknown)));
+    }
+
+    #[tokio::test]
+    async fn instrumented_find_passes_through_operation_result() {
+        let id = Uuid::new_v4();
+
+        let result = instrumentation::find_all_events::<User, _>("test", &id, async {
+            Err(Error::NotFound(id))
+        })
+        .await;
+
+        assert!(matches!(result, Err(Error::NotFound(..))));
+    }
+
+    #[cfg(feature = "instrumentation")]
+    #[tokio::test]
+    async fn serialization_spans_nest_under_save_span() {
+        use crate::aggregate::EventSourced;
+        use crate::repository::interface::Repository;
+        use crate::repository::memory::MemoryRepository;
+
+        let recorder = SpanRecorder::default();
+        let _guard = tracing::subscriber::set_default(recorder.clone());
+
+        let id = Uuid::new_v4();
+        let mut user = User::default();
+        user.update(UserEvent::UserRegistered { id }).await;
+        MemoryRepository::default().save(&mut user).await.unwrap();
+
+        let spans = recorder.spans.lock().unwrap().clone();
+        let (save_id, _, _) = spans
+            .iter()
+            .find(|(_, name, _)| *name == "event_sourcing.repository.save")
+            .unwrap();
+        let (_, _, parent_id) = spans
+            .iter()
+            .find(|(_, name, _)| *name == "event_sourcing.serialize")
+            .unwrap();
+        assert_eq!(parent_id, &Some(*save_id));
+    }
+
+    // id, name and parent id of a span
+    #[cfg(feature = "instrumentation")]
+    type RecordedSpan = (u64, &'static str, Option<u64>);
+
+    // Records every span with its parent, which is the innermost entered span
+    #[cfg(feature = "instrumentation")]
+    #[derive(Clone, Default)]
+    struct SpanRecorder {
+        spans: std::sync::Arc<std::sync::Mutex<Vec<RecordedSpan>>>,
+        entered: std::sync::Arc<std::sync::Mutex<Vec<u64>>>,
+    }
+
+    #[cfg(feature = "instrumentation")]
+    impl tracing::Subscriber for SpanRecorder {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attributes: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut spans = self.spans.lock().unwrap();
+            let id = spans.len() as u64 + 1;
+            let parent = match attributes.parent() {
+                Some(parent) => Some(parent.into_u64()),
+                None if attributes.is_contextual() => self.entered.lock().unwrap().last().copied(),
+                None => None,
+            };
+            spans.push((id, attributes.metadata().name(), parent));
+
+            tracing::span::Id::from_u64(id)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, _: &tracing::Event<'_>) {}
+
+        fn enter(&self, span: &tracing::span::Id) {
+            self.entered.lock().unwrap().push(span.into_u64());
+        }
+
+        fn exit(&self, _: &tracing::span::Id) {
+            self.entered.lock().unwrap().pop();
+        }
+    }
+}
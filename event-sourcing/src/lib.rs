@@ -1,5 +1,10 @@
 pub mod aggregate;
 pub mod envelope;
 pub mod event;
+mod instrumentation;
 pub mod repository;
+pub mod scheduler;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(test)]
 mod test;
@@ -0,0 +1,1051 @@
+// Behaviours every `Repository<A>` implementation is expected to share. Backends run these
+// against a live instance, either all at once with `run_all` or check by check.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::aggregate::EventSourced;
+use crate::envelope::Envelope;
+use crate::event::{DomainEvent, EventApplier};
+use crate::repository::error::{Error, ErrorKind};
+use crate::repository::interface::{
+    IdempotentRepository, Repository, Reservations, ReservingRepository, SaveOutcome,
+    StreamLifecycle, StreamQuery,
+};
+use crate::repository::query::{EventFilter, Page};
+
+pub async fn run_all<R>(repository: R)
+where
+    R: Repository<Order> + Repository<Shipment>,
+{
+    events_are_found_in_sequence_order(repository.clone()).await;
+    missing_stream_is_not_found(repository.clone()).await;
+    saving_without_pending_events_writes_nothing(repository.clone()).await;
+    pending_events_are_drained_after_save(repository.clone()).await;
+    aggregate_names_are_isolated(repository.clone()).await;
+    conflicting_sequences_are_rejected(repository.clone()).await;
+    events_round_trip_without_loss(repository.clone()).await;
+    large_commits_are_saved_whole(repository.clone()).await;
+    many_streams_are_found_grouped_by_aggregate(repository).await;
+}
+
+pub async fn events_are_found_in_sequence_order<R: Repository<Order>>(mut repository: R) {
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "coffee beans").await;
+    order.amend(2, None).await;
+    repository.save(&mut order).await.unwrap();
+
+    let mut order = Order::load(find::<Order, _>(&repository, &id).await).await;
+    order.amend(3, Some("ground")).await;
+    repository.save(&mut order).await.unwrap();
+
+    let envelopes = find::<Order, _>(&repository, &id).await;
+    let sequences = envelopes
+        .iter()
+        .map(|envelope| envelope.aggregate_sequence)
+        .collect::<Vec<i64>>();
+
+    assert_eq!(sequences, vec![1, 2, 3]);
+    assert_eq!(Order::load(envelopes).await.quantity, 3);
+}
+
+pub async fn missing_stream_is_not_found<R: Repository<Order>>(repository: R) {
+    let id = Uuid::new_v4();
+
+    let result: Result<Vec<Envelope<Order>>, Error> = repository.find_all_events(&id).await;
+
+    assert!(matches!(result, Err(Error::NotFound(missing_id)) if missing_id == id));
+}
+
+pub async fn saving_without_pending_events_writes_nothing<R: Repository<Order>>(mut repository: R) {
+    let mut unsaved = Order::default();
+    repository.save(&mut unsaved).await.unwrap();
+
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository.save(&mut order).await.unwrap();
+    repository.save(&mut order).await.unwrap();
+
+    assert_eq!(find::<Order, _>(&repository, &id).await.len(), 1);
+}
+
+pub async fn pending_events_are_drained_after_save<R: Repository<Order>>(mut repository: R) {
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    order.amend(5, None).await;
+
+    repository.save(&mut order).await.unwrap();
+
+    assert!(order.get_pending_events().is_empty());
+    assert_eq!(order.get_id(), id);
+    assert_eq!(order.get_sequence(), 2);
+}
+
+pub async fn aggregate_names_are_isolated<R>(mut repository: R)
+where
+    R: Repository<Order> + Repository<Shipment>,
+{
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    order.amend(2, None).await;
+    repository.save(&mut order).await.unwrap();
+
+    let mut shipment = Shipment::default();
+    shipment.dispatch(id, "Seoul").await;
+    repository.save(&mut shipment).await.unwrap();
+
+    let orders = find::<Order, _>(&repository, &id).await;
+    let shipments = find::<Shipment, _>(&repository, &id).await;
+
+    assert_eq!(orders.len(), 2);
+    assert_eq!(shipments.len(), 1);
+    assert_eq!(
+        shipments[0].event,
+        ShipmentEvent::ShipmentDispatched {
+            id,
+            destination: String::from("Seoul"),
+        }
+    );
+}
+
+pub async fn conflicting_sequences_are_rejected<R: Repository<Order>>(mut repository: R) {
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository.save(&mut order).await.unwrap();
+
+    let mut first = Order::load(find::<Order, _>(&repository, &id).await).await;
+    let mut second = Order::load(find::<Order, _>(&repository, &id).await).await;
+    first.amend(2, None).await;
+    second.amend(7, None).await;
+    second.amend(8, None).await;
+
+    repository.save(&mut first).await.unwrap();
+    let error = repository.save(&mut second).await.unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::Conflict);
+
+    let envelopes = find::<Order, _>(&repository, &id).await;
+    assert_eq!(envelopes.len(), 2);
+    assert_eq!(Order::load(envelopes).await.quantity, 2);
+}
+
+pub async fn events_round_trip_without_loss<R: Repository<Order>>(mut repository: R) {
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "그라인더 \"burr\" ☕").await;
+    order.amend(i64::MAX, Some("")).await;
+
+    let expected = order
+        .get_mut_pending_events()
+        .iter_mut()
+        .map(|envelope| {
+            envelope
+                .metadata
+                .insert(String::from("correlation-id"), Uuid::new_v4().to_string());
+            (
+                envelope.id,
+                envelope.aggregate_id,
+                envelope.aggregate_sequence,
+                envelope.event.clone(),
+                envelope.metadata.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    repository.save(&mut order).await.unwrap();
+
+    let actual = find::<Order, _>(&repository, &id)
+        .await
+        .into_iter()
+        .map(|envelope| {
+            (
+                envelope.id,
+                envelope.aggregate_id,
+                envelope.aggregate_sequence,
+                envelope.event,
+                envelope.metadata,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(actual, expected);
+}
+
+pub async fn large_commits_are_saved_whole<R: Repository<Order>>(mut repository: R) {
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    for quantity in 1..2500 {
+        order.amend(quantity, None).await;
+    }
+
+    repository.save(&mut order).await.unwrap();
+
+    let envelopes = find::<Order, _>(&repository, &id).await;
+    assert_eq!(envelopes.len(), 2500);
+    assert!(envelopes
+        .iter()
+        .zip(1..)
+        .all(|(envelope, sequence)| envelope.aggregate_sequence == sequence));
+    assert_eq!(Order::load(envelopes).await.quantity, 2499);
+}
+
+pub async fn many_streams_are_found_grouped_by_aggregate<R>(mut repository: R)
+where
+    R: Repository<Order> + Repository<Shipment>,
+{
+    let ids = [Uuid::new_v4(), Uuid::new_v4()];
+    for (id, quantity) in ids.iter().zip([2, 5]) {
+        let mut order = Order::default();
+        order.place(*id, "tea").await;
+        order.amend(quantity, None).await;
+        repository.save(&mut order).await.unwrap();
+    }
+    let shipment_id = Uuid::new_v4();
+    let mut shipment = Shipment::default();
+    shipment.dispatch(shipment_id, "Busan").await;
+    repository.save(&mut shipment).await.unwrap();
+
+    let missing_id = Uuid::new_v4();
+    let streams: HashMap<Uuid, Vec<Envelope<Order>>> = repository
+        .find_many_events(&[ids[0], ids[1], shipment_id, missing_id])
+        .await
+        .unwrap();
+    let orders = Order::load_many(streams).await;
+
+    assert_eq!(orders.len(), 2);
+    assert_eq!(orders[&ids[0]].quantity, 2);
+    assert_eq!(orders[&ids[1]].quantity, 5);
+    assert_eq!(orders[&ids[1]].get_sequence(), 2);
+
+    let none: HashMap<Uuid, Vec<Envelope<Order>>> = repository.find_many_events(&[]).await.unwrap();
+    assert!(none.is_empty());
+}
+
+pub async fn run_lifecycle<R>(repository: R)
+where
+    R: StreamLifecycle<Order> + Repository<Shipment>,
+{
+    tombstoned_stream_rejects_saves_and_loads(repository.clone()).await;
+    archived_stream_rejects_saves_and_loads(repository.clone()).await;
+    tombstoned_stream_stays_deleted_after_archival(repository.clone()).await;
+    deleted_stream_is_not_found(repository.clone()).await;
+    lifecycle_of_missing_stream_is_not_found(repository.clone()).await;
+    lifecycle_is_isolated_by_aggregate_name(repository.clone()).await;
+    ended_streams_are_left_out_of_many_streams(repository).await;
+}
+
+pub async fn tombstoned_stream_rejects_saves_and_loads<R: StreamLifecycle<Order>>(
+    mut repository: R,
+) {
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository.save(&mut order).await.unwrap();
+
+    repository.tombstone(&id).await.unwrap();
+    repository.tombstone(&id).await.unwrap();
+
+    order.amend(2, None).await;
+    let saved = repository.save(&mut order).await;
+    let found: Result<Vec<Envelope<Order>>, Error> = repository.find_all_events(&id).await;
+
+    assert!(matches!(saved, Err(Error::Deleted(deleted_id)) if deleted_id == id));
+    assert!(matches!(found, Err(Error::Deleted(deleted_id)) if deleted_id == id));
+}
+
+pub async fn archived_stream_rejects_saves_and_loads<R: StreamLifecycle<Order>>(mut repository: R) {
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository.save(&mut order).await.unwrap();
+
+    repository.archive(&id).await.unwrap();
+    repository.archive(&id).await.unwrap();
+
+    order.amend(2, None).await;
+    let saved = repository.save(&mut order).await;
+    let found: Result<Vec<Envelope<Order>>, Error> = repository.find_all_events(&id).await;
+    let tombstoned = repository.tombstone(&id).await;
+
+    assert!(matches!(saved, Err(Error::Archived(archived_id)) if archived_id == id));
+    assert!(matches!(found, Err(Error::Archived(archived_id)) if archived_id == id));
+    assert!(matches!(tombstoned, Err(Error::Archived(..))));
+}
+
+pub async fn tombstoned_stream_stays_deleted_after_archival<R: StreamLifecycle<Order>>(
+    mut repository: R,
+) {
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository.save(&mut order).await.unwrap();
+
+    repository.tombstone(&id).await.unwrap();
+    repository.archive(&id).await.unwrap();
+
+    let found: Result<Vec<Envelope<Order>>, Error> = repository.find_all_events(&id).await;
+
+    assert!(matches!(found, Err(Error::Deleted(..))));
+}
+
+pub async fn deleted_stream_is_not_found<R: StreamLifecycle<Order>>(mut repository: R) {
+    let tombstoned_id = Uuid::new_v4();
+    let archived_id = Uuid::new_v4();
+    for (id, archive) in [(tombstoned_id, false), (archived_id, true)] {
+        let mut order = Order::default();
+        order.place(id, "tea").await;
+        repository.save(&mut order).await.unwrap();
+        match archive {
+            true => repository.archive(&id).await.unwrap(),
+            false => repository.tombstone(&id).await.unwrap(),
+        }
+
+        repository.delete(&id).await.unwrap();
+
+        let found: Result<Vec<Envelope<Order>>, Error> = repository.find_all_events(&id).await;
+        assert!(matches!(found, Err(Error::NotFound(..))));
+    }
+
+    let mut order = Order::default();
+    order.place(archived_id, "coffee").await;
+    repository.save(&mut order).await.unwrap();
+
+    assert_eq!(find::<Order, _>(&repository, &archived_id).await.len(), 1);
+}
+
+pub async fn lifecycle_of_missing_stream_is_not_found<R: StreamLifecycle<Order>>(
+    mut repository: R,
+) {
+    let id = Uuid::new_v4();
+
+    let tombstoned = repository.tombstone(&id).await;
+    let archived = repository.archive(&id).await;
+    let deleted = repository.delete(&id).await;
+
+    assert!(matches!(tombstoned, Err(Error::NotFound(..))));
+    assert!(matches!(archived, Err(Error::NotFound(..))));
+    assert!(matches!(deleted, Err(Error::NotFound(..))));
+}
+
+pub async fn lifecycle_is_isolated_by_aggregate_name<R>(mut repository: R)
+where
+    R: StreamLifecycle<Order> + Repository<Shipment>,
+{
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository.save(&mut order).await.unwrap();
+    let mut shipment = Shipment::default();
+    shipment.dispatch(id, "Seoul").await;
+    repository.save(&mut shipment).await.unwrap();
+
+    StreamLifecycle::<Order>::tombstone(&mut repository, &id)
+        .await
+        .unwrap();
+
+    assert_eq!(find::<Shipment, _>(&repository, &id).await.len(), 1);
+}
+
+pub async fn ended_streams_are_left_out_of_many_streams<R: StreamLifecycle<Order>>(
+    mut repository: R,
+) {
+    let ids = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+    for id in ids {
+        let mut order = Order::default();
+        order.place(id, "tea").await;
+        repository.save(&mut order).await.unwrap();
+    }
+
+    repository.tombstone(&ids[1]).await.unwrap();
+    repository.archive(&ids[2]).await.unwrap();
+
+    let streams = repository.find_many_events(&ids).await.unwrap();
+
+    assert_eq!(streams.keys().collect::<Vec<_>>(), vec![&ids[0]]);
+}
+
+// Other writers may share the store, so these only assert on the streams they create
+pub async fn run_query<R>(repository: R)
+where
+    R: StreamQuery<Order> + StreamLifecycle<Order>,
+{
+    streams_are_listed_in_pages_and_counted(repository.clone()).await;
+    events_are_found_by_name_version_and_creation_time(repository.clone()).await;
+    events_are_found_by_metadata(repository.clone()).await;
+    ended_streams_are_left_out_of_queries(repository).await;
+}
+
+pub async fn streams_are_listed_in_pages_and_counted<R: StreamQuery<Order>>(mut repository: R) {
+    let count = repository.count_streams().await.unwrap();
+    let mut ids = Vec::new();
+    for _ in 0..3 {
+        let mut order = Order::default();
+        order.place(Uuid::new_v4(), "tea").await;
+        repository.save(&mut order).await.unwrap();
+        ids.push(order.get_id());
+    }
+
+    let listed = list_all(&repository).await;
+
+    assert!(repository.count_streams().await.unwrap() >= count + 3);
+    assert!(ids.iter().all(|id| listed.contains(id)));
+    assert!(listed.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+pub async fn events_are_found_by_name_version_and_creation_time<R: StreamQuery<Order>>(
+    mut repository: R,
+) {
+    let started_at = SystemTime::now();
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    order.amend(2, None).await;
+    order.amend(3, None).await;
+    repository.save(&mut order).await.unwrap();
+
+    let amended = EventFilter::default()
+        .with_event_name("OrderAmended")
+        .with_event_version("1.0.0")
+        .with_created_from(started_at);
+    let found = repository
+        .find_events(&amended, Page::new(0, 1000))
+        .await
+        .unwrap();
+    let before = repository
+        .find_events(
+            &EventFilter::default().with_created_until(started_at),
+            Page::new(0, 1000),
+        )
+        .await
+        .unwrap();
+    let unknown_version = repository
+        .find_events(
+            &EventFilter::default().with_event_version("0"),
+            Page::default(),
+        )
+        .await
+        .unwrap();
+
+    let sequences = found
+        .iter()
+        .filter(|envelope| envelope.aggregate_id == id)
+        .map(|envelope| envelope.aggregate_sequence)
+        .collect::<Vec<i64>>();
+    assert_eq!(sequences, vec![2, 3]);
+    assert!(found
+        .iter()
+        .all(|envelope| matches!(envelope.event, OrderEvent::OrderAmended { .. })));
+    assert!(found
+        .windows(2)
+        .all(|pair| pair[0].created_at <= pair[1].created_at));
+    assert!(before.iter().all(|envelope| envelope.aggregate_id != id));
+    assert!(unknown_version.is_empty());
+}
+
+pub async fn events_are_found_by_metadata<R: StreamQuery<Order>>(mut repository: R) {
+    let started_at = SystemTime::now();
+    let ids = [Uuid::new_v4(), Uuid::new_v4()];
+    for (id, channel) in ids.iter().zip(["web", "app"]) {
+        let mut order = Order::default();
+        order.place(*id, "tea").await;
+        order.amend(2, None).await;
+        for envelope in order.get_mut_pending_events() {
+            envelope
+                .metadata
+                .insert(String::from("channel"), String::from(channel));
+            envelope
+                .metadata
+                .insert(String::from("region"), String::from("kr"));
+        }
+        repository.save(&mut order).await.unwrap();
+    }
+
+    let web = repository
+        .find_events(
+            &EventFilter::default()
+                .with_created_from(started_at)
+                .with_metadata("channel", "web")
+                .with_metadata("region", "kr"),
+            Page::new(0, 1000),
+        )
+        .await
+        .unwrap();
+    let unknown_region = repository
+        .find_events(
+            &EventFilter::default()
+                .with_created_from(started_at)
+                .with_metadata("channel", "web")
+                .with_metadata("region", "jp"),
+            Page::new(0, 1000),
+        )
+        .await
+        .unwrap();
+
+    let found = web
+        .iter()
+        .filter(|envelope| ids.contains(&envelope.aggregate_id))
+        .map(|envelope| (envelope.aggregate_id, envelope.aggregate_sequence))
+        .collect::<Vec<_>>();
+    assert_eq!(found, vec![(ids[0], 1), (ids[0], 2)]);
+    assert!(unknown_region
+        .iter()
+        .all(|envelope| !ids.contains(&envelope.aggregate_id)));
+}
+
+pub async fn ended_streams_are_left_out_of_queries<R>(mut repository: R)
+where
+    R: StreamQuery<Order> + StreamLifecycle<Order>,
+{
+    let started_at = SystemTime::now();
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository.save(&mut order).await.unwrap();
+
+    repository.tombstone(&id).await.unwrap();
+
+    let listed = list_all(&repository).await;
+    let found = repository
+        .find_events(
+            &EventFilter::default().with_created_from(started_at),
+            Page::new(0, 1000),
+        )
+        .await
+        .unwrap();
+
+    assert!(!listed.contains(&id));
+    assert!(found.iter().all(|envelope| envelope.aggregate_id != id));
+}
+
+// Takes a way to scope repositories sharing one store to a tenant
+pub async fn run_tenancy<R, F>(scope: F)
+where
+    R: StreamQuery<Order> + StreamLifecycle<Order>,
+    F: Fn(Uuid) -> R,
+{
+    same_aggregate_id_is_isolated_by_tenant(scope(Uuid::new_v4()), scope(Uuid::new_v4())).await;
+    streams_of_other_tenants_are_not_found(scope(Uuid::new_v4()), scope(Uuid::new_v4())).await;
+    queries_are_isolated_by_tenant(scope(Uuid::new_v4()), scope(Uuid::new_v4())).await;
+    lifecycle_is_isolated_by_tenant(scope(Uuid::new_v4()), scope(Uuid::new_v4())).await;
+}
+
+pub async fn same_aggregate_id_is_isolated_by_tenant<R: Repository<Order>>(
+    mut repository: R,
+    mut other_repository: R,
+) {
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository.save(&mut order).await.unwrap();
+
+    let mut other_order = Order::default();
+    other_order.place(id, "coffee beans").await;
+    other_order.amend(4, None).await;
+    other_repository.save(&mut other_order).await.unwrap();
+
+    let envelopes = find::<Order, _>(&repository, &id).await;
+    let other_envelopes = find::<Order, _>(&other_repository, &id).await;
+
+    assert_eq!(envelopes.len(), 1);
+    assert_eq!(other_envelopes.len(), 2);
+    assert_ne!(envelopes[0].tenant_id, other_envelopes[0].tenant_id);
+    assert!(other_envelopes
+        .iter()
+        .all(|envelope| envelope.tenant_id == other_envelopes[0].tenant_id));
+}
+
+pub async fn streams_of_other_tenants_are_not_found<R: Repository<Order>>(
+    mut repository: R,
+    other_repository: R,
+) {
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository.save(&mut order).await.unwrap();
+
+    let result: Result<Vec<Envelope<Order>>, Error> = other_repository.find_all_events(&id).await;
+    let streams: HashMap<Uuid, Vec<Envelope<Order>>> =
+        other_repository.find_many_events(&[id]).await.unwrap();
+
+    assert!(matches!(result, Err(Error::NotFound(..))));
+    assert!(streams.is_empty());
+}
+
+pub async fn queries_are_isolated_by_tenant<R: StreamQuery<Order>>(
+    mut repository: R,
+    other_repository: R,
+) {
+    let started_at = SystemTime::now();
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository.save(&mut order).await.unwrap();
+
+    let found = other_repository
+        .find_events(
+            &EventFilter::default().with_created_from(started_at),
+            Page::new(0, 1000),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(list_all(&repository).await, vec![id]);
+    assert_eq!(repository.count_streams().await.unwrap(), 1);
+    assert!(list_all(&other_repository).await.is_empty());
+    assert_eq!(other_repository.count_streams().await.unwrap(), 0);
+    assert!(found.is_empty());
+}
+
+pub async fn lifecycle_is_isolated_by_tenant<R: StreamLifecycle<Order>>(
+    mut repository: R,
+    mut other_repository: R,
+) {
+    let id = Uuid::new_v4();
+    for repository in [&mut repository, &mut other_repository] {
+        let mut order = Order::default();
+        order.place(id, "tea").await;
+        repository.save(&mut order).await.unwrap();
+    }
+
+    repository.tombstone(&id).await.unwrap();
+
+    let result: Result<Vec<Envelope<Order>>, Error> = repository.find_all_events(&id).await;
+    assert!(matches!(result, Err(Error::Deleted(..))));
+    assert_eq!(find::<Order, _>(&other_repository, &id).await.len(), 1);
+
+    other_repository.delete(&id).await.unwrap();
+
+    let result: Result<Vec<Envelope<Order>>, Error> = repository.find_all_events(&id).await;
+    assert!(matches!(result, Err(Error::Deleted(..))));
+}
+
+pub async fn run_idempotency<R>(repository: R)
+where
+    R: IdempotentRepository<Order> + IdempotentRepository<Shipment>,
+{
+    saving_with_used_key_writes_nothing(repository.clone()).await;
+    idempotency_keys_are_isolated_by_aggregate_name(repository.clone()).await;
+    later_saves_with_new_keys_are_written(repository).await;
+}
+
+pub async fn saving_with_used_key_writes_nothing<R: IdempotentRepository<Order>>(
+    mut repository: R,
+) {
+    let key = Uuid::new_v4().to_string();
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    let saved = repository.save_with_key(&mut order, &key).await.unwrap();
+
+    // a retried command creating the aggregate again under a new ID
+    let retried_id = Uuid::new_v4();
+    let mut retried = Order::default();
+    retried.place(retried_id, "tea").await;
+    let duplicate = repository.save_with_key(&mut retried, &key).await.unwrap();
+
+    let result: Result<Vec<Envelope<Order>>, Error> = repository.find_all_events(&retried_id).await;
+    assert_eq!(saved, SaveOutcome::Saved);
+    assert_eq!(duplicate, SaveOutcome::Duplicate(id));
+    assert!(retried.get_pending_events().is_empty());
+    assert!(matches!(result, Err(Error::NotFound(..))));
+    assert_eq!(find::<Order, _>(&repository, &id).await.len(), 1);
+}
+
+pub async fn idempotency_keys_are_isolated_by_aggregate_name<R>(mut repository: R)
+where
+    R: IdempotentRepository<Order> + IdempotentRepository<Shipment>,
+{
+    let key = Uuid::new_v4().to_string();
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository.save_with_key(&mut order, &key).await.unwrap();
+
+    let mut shipment = Shipment::default();
+    shipment.dispatch(id, "Seoul").await;
+    let outcome = repository.save_with_key(&mut shipment, &key).await.unwrap();
+
+    assert_eq!(outcome, SaveOutcome::Saved);
+    assert_eq!(find::<Shipment, _>(&repository, &id).await.len(), 1);
+}
+
+pub async fn later_saves_with_new_keys_are_written<R: IdempotentRepository<Order>>(
+    mut repository: R,
+) {
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository
+        .save_with_key(&mut order, &Uuid::new_v4().to_string())
+        .await
+        .unwrap();
+
+    let mut order = Order::load(find::<Order, _>(&repository, &id).await).await;
+    order.amend(2, None).await;
+    let outcome = repository
+        .save_with_key(&mut order, &Uuid::new_v4().to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(outcome, SaveOutcome::Saved);
+    assert_eq!(find::<Order, _>(&repository, &id).await.len(), 2);
+}
+
+pub async fn run_reservation<R>(repository: R)
+where
+    R: ReservingRepository<Order> + ReservingRepository<Shipment> + StreamLifecycle<Order>,
+{
+    keys_reserved_by_another_aggregate_are_rejected(repository.clone()).await;
+    holder_reserves_its_key_again(repository.clone()).await;
+    released_keys_are_reserved_by_others(repository.clone()).await;
+    reservations_are_isolated_by_aggregate_name(repository.clone()).await;
+    deleted_streams_free_their_keys(repository).await;
+}
+
+pub async fn keys_reserved_by_another_aggregate_are_rejected<R: ReservingRepository<Order>>(
+    mut repository: R,
+) {
+    let key = Uuid::new_v4().to_string();
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository
+        .save_with_reservations(&mut order, &Reservations::default().reserve("coupon", &key))
+        .await
+        .unwrap();
+
+    let other_id = Uuid::new_v4();
+    let mut other = Order::default();
+    other.place(other_id, "tea").await;
+    let result = repository
+        .save_with_reservations(&mut other, &Reservations::default().reserve("coupon", &key))
+        .await;
+
+    let found: Result<Vec<Envelope<Order>>, Error> = repository.find_all_events(&other_id).await;
+    assert!(matches!(result, Err(Error::Reserved { aggregate_id, .. }) if aggregate_id == id));
+    assert!(matches!(found, Err(Error::NotFound(..))));
+    assert_eq!(
+        ReservingRepository::<Order>::find_reservation(&repository, "coupon", &key)
+            .await
+            .unwrap(),
+        Some(id)
+    );
+}
+
+pub async fn holder_reserves_its_key_again<R: ReservingRepository<Order>>(mut repository: R) {
+    let key = Uuid::new_v4().to_string();
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    let reservations = Reservations::default().reserve("coupon", &key);
+    repository
+        .save_with_reservations(&mut order, &reservations)
+        .await
+        .unwrap();
+
+    let mut order = Order::load(find::<Order, _>(&repository, &id).await).await;
+    order.amend(2, None).await;
+    repository
+        .save_with_reservations(&mut order, &reservations)
+        .await
+        .unwrap();
+
+    assert_eq!(find::<Order, _>(&repository, &id).await.len(), 2);
+}
+
+pub async fn released_keys_are_reserved_by_others<R: ReservingRepository<Order>>(
+    mut repository: R,
+) {
+    let key = Uuid::new_v4().to_string();
+    let other_key = Uuid::new_v4().to_string();
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository
+        .save_with_reservations(&mut order, &Reservations::default().reserve("coupon", &key))
+        .await
+        .unwrap();
+
+    // swapping the key of the aggregate for another one
+    let mut order = Order::load(find::<Order, _>(&repository, &id).await).await;
+    order.amend(2, None).await;
+    let swapped = Reservations::default()
+        .release("coupon", &key)
+        .reserve("coupon", &other_key);
+    repository
+        .save_with_reservations(&mut order, &swapped)
+        .await
+        .unwrap();
+
+    let mut other = Order::default();
+    other.place(Uuid::new_v4(), "tea").await;
+    let result = repository
+        .save_with_reservations(&mut other, &Reservations::default().reserve("coupon", &key))
+        .await;
+
+    assert!(result.is_ok());
+    assert_eq!(
+        ReservingRepository::<Order>::find_reservation(&repository, "coupon", &other_key)
+            .await
+            .unwrap(),
+        Some(id)
+    );
+}
+
+pub async fn reservations_are_isolated_by_aggregate_name<R>(mut repository: R)
+where
+    R: ReservingRepository<Order> + ReservingRepository<Shipment>,
+{
+    let key = Uuid::new_v4().to_string();
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository
+        .save_with_reservations(&mut order, &Reservations::default().reserve("code", &key))
+        .await
+        .unwrap();
+
+    let mut shipment = Shipment::default();
+    shipment.dispatch(Uuid::new_v4(), "Seoul").await;
+    let result = repository
+        .save_with_reservations(
+            &mut shipment,
+            &Reservations::default().reserve("code", &key),
+        )
+        .await;
+
+    assert!(result.is_ok());
+}
+
+pub async fn deleted_streams_free_their_keys<R>(mut repository: R)
+where
+    R: ReservingRepository<Order> + StreamLifecycle<Order>,
+{
+    let key = Uuid::new_v4().to_string();
+    let id = Uuid::new_v4();
+    let mut order = Order::default();
+    order.place(id, "tea").await;
+    repository
+        .save_with_reservations(&mut order, &Reservations::default().reserve("coupon", &key))
+        .await
+        .unwrap();
+
+    StreamLifecycle::<Order>::delete(&mut repository, &id)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        ReservingRepository::<Order>::find_reservation(&repository, "coupon", &key)
+            .await
+            .unwrap(),
+        None
+    );
+}
+
+async fn list_all<R: StreamQuery<Order>>(repository: &R) -> Vec<Uuid> {
+    let mut listed = Vec::new();
+    let mut page = Page::new(0, 50);
+    loop {
+        let ids = repository.list_aggregate_ids(page).await.unwrap();
+        if ids.is_empty() {
+            return listed;
+        }
+        listed.extend(ids);
+        page = page.next();
+    }
+}
+
+async fn find<A: EventSourced, R: Repository<A>>(repository: &R, id: &Uuid) -> Vec<Envelope<A>> {
+    repository.find_all_events(id).await.unwrap()
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Order {
+    id: Uuid,
+    sequence: i64,
+    item: String,
+    quantity: i64,
+    note: Option<String>,
+    pending_events: Vec<Envelope<Self>>,
+}
+
+impl Order {
+    pub async fn place(&mut self, id: Uuid, item: &str) {
+        self.update(OrderEvent::OrderPlaced {
+            id,
+            item: String::from(item),
+        })
+        .await;
+    }
+
+    pub async fn amend(&mut self, quantity: i64, note: Option<&str>) {
+        self.update(OrderEvent::OrderAmended {
+            quantity,
+            note: note.map(String::from),
+        })
+        .await;
+    }
+}
+
+#[async_trait]
+impl EventSourced for Order {
+    type Event = OrderEvent;
+    type Error = ConformanceError;
+
+    fn get_name() -> String {
+        String::from("ConformanceOrder")
+    }
+    fn get_id(&self) -> Uuid {
+        self.id
+    }
+    fn get_sequence(&self) -> i64 {
+        self.sequence
+    }
+    fn set_sequence(&mut self, seq: i64) {
+        self.sequence = seq
+    }
+    fn get_pending_events(&self) -> &Vec<Envelope<Self>> {
+        &self.pending_events
+    }
+    fn get_mut_pending_events(&mut self) -> &mut Vec<Envelope<Self>> {
+        &mut self.pending_events
+    }
+    fn add_pending_event(&mut self, event: Envelope<Self>) {
+        self.pending_events.push(event)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum OrderEvent {
+    OrderPlaced { id: Uuid, item: String },
+    OrderAmended { quantity: i64, note: Option<String> },
+}
+
+impl DomainEvent for OrderEvent {
+    fn get_name(&self) -> String {
+        match self {
+            OrderEvent::OrderPlaced { .. } => String::from("OrderPlaced"),
+            OrderEvent::OrderAmended { .. } => String::from("OrderAmended"),
+        }
+    }
+    fn get_version(&self) -> String {
+        match self {
+            OrderEvent::OrderPlaced { .. } => String::from("1.0.0"),
+            OrderEvent::OrderAmended { .. } => String::from("1.0.0"),
+        }
+    }
+}
+
+#[async_trait]
+impl EventApplier<Order> for Order {
+    async fn apply(&mut self, event: OrderEvent) {
+        match event {
+            OrderEvent::OrderPlaced { id, item } => {
+                self.id = id;
+                self.item = item;
+                self.quantity = 1;
+            }
+            OrderEvent::OrderAmended { quantity, note } => {
+                self.quantity = quantity;
+                self.note = note;
+            }
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Shipment {
+    id: Uuid,
+    sequence: i64,
+    destination: String,
+    pending_events: Vec<Envelope<Self>>,
+}
+
+impl Shipment {
+    pub async fn dispatch(&mut self, id: Uuid, destination: &str) {
+        self.update(ShipmentEvent::ShipmentDispatched {
+            id,
+            destination: String::from(destination),
+        })
+        .await;
+    }
+}
+
+#[async_trait]
+impl EventSourced for Shipment {
+    type Event = ShipmentEvent;
+    type Error = ConformanceError;
+
+    fn get_name() -> String {
+        String::from("ConformanceShipment")
+    }
+    fn get_id(&self) -> Uuid {
+        self.id
+    }
+    fn get_sequence(&self) -> i64 {
+        self.sequence
+    }
+    fn set_sequence(&mut self, seq: i64) {
+        self.sequence = seq
+    }
+    fn get_pending_events(&self) -> &Vec<Envelope<Self>> {
+        &self.pending_events
+    }
+    fn get_mut_pending_events(&mut self) -> &mut Vec<Envelope<Self>> {
+        &mut self.pending_events
+    }
+    fn add_pending_event(&mut self, event: Envelope<Self>) {
+        self.pending_events.push(event)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ShipmentEvent {
+    ShipmentDispatched { id: Uuid, destination: String },
+}
+
+impl DomainEvent for ShipmentEvent {
+    fn get_name(&self) -> String {
+        match self {
+            ShipmentEvent::ShipmentDispatched { .. } => String::from("ShipmentDispatched"),
+        }
+    }
+    fn get_version(&self) -> String {
+        match self {
+            ShipmentEvent::ShipmentDispatched { .. } => String::from("1.0.0"),
+        }
+    }
+}
+
+#[async_trait]
+impl EventApplier<Shipment> for Shipment {
+    async fn apply(&mut self, event: ShipmentEvent) {
+        match event {
+            ShipmentEvent::ShipmentDispatched { id, destination } => {
+                self.id = id;
+                self.destination = destination;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConformanceError {}
+
+impl Display for ConformanceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ConformanceError")
+    }
+}
+
+impl std::error::Error for ConformanceError {}
@@ -1,3 +1,4 @@
+use sqlx::mysql::MySqlDatabaseError;
 use uuid::Uuid;
 
 #[derive(Debug, thiserror::Error)]
@@ -17,9 +18,138 @@ pub enum Error {
     #[error("{0}")]
     Execution(Box<dyn std::error::Error + Send + Sync + 'static>),
 
+    // The commit failed without telling whether it was applied, e.g. the connection dropped
+    // after COMMIT was sent, and the saved events could not be found afterwards either
+    #[error("Outcome of the commit is unknown: {0}")]
+    Commit(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("{0}")]
+    Conflict(Box<dyn std::error::Error + Send + Sync + 'static>),
+
     #[error("No entity found with ID {0}")]
     NotFound(Uuid),
 
+    #[error("Entity with ID {0} was deleted")]
+    Deleted(Uuid),
+
+    #[error("Entity with ID {0} was archived")]
+    Archived(Uuid),
+
+    #[error("Key {key} of {scope} is already reserved by entity with ID {aggregate_id}")]
+    Reserved {
+        scope: String,
+        key: String,
+        aggregate_id: Uuid,
+    },
+
     #[error("Unknown repository error")]
     Unknown,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    // worth retrying as is, e.g. deadlocks, lock timeouts and dropped connections
+    Transient,
+    // another writer got there first, so the caller has to reload and decide again
+    Conflict,
+    // retrying will not help
+    Permanent,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Conflict(..) => ErrorKind::Conflict,
+            Error::Connection(..) => ErrorKind::Transient,
+            Error::Transaction(source) | Error::Execution(source) => source
+                .downcast_ref::<sqlx::Error>()
+                .map(classify)
+                .unwrap_or(ErrorKind::Permanent),
+            _ => ErrorKind::Permanent,
+        }
+    }
+
+    pub fn is_transient(&self) -> bool {
+        self.kind() == ErrorKind::Transient
+    }
+
+    pub(crate) fn execution(error: sqlx::Error) -> Self {
+        match classify(&error) {
+            ErrorKind::Conflict => Error::Conflict(Box::new(error)),
+            _ => Error::Execution(Box::new(error)),
+        }
+    }
+
+    pub(crate) fn transaction(error: sqlx::Error) -> Self {
+        match classify(&error) {
+            ErrorKind::Conflict => Error::Conflict(Box::new(error)),
+            _ => Error::Transaction(Box::new(error)),
+        }
+    }
+}
+
+fn classify(error: &sqlx::Error) -> ErrorKind {
+    match error {
+        sqlx::Error::Io(..) | sqlx::Error::PoolTimedOut | sqlx::Error::WorkerCrashed => {
+            ErrorKind::Transient
+        }
+        sqlx::Error::Database(error) if error.is_unique_violation() => ErrorKind::Conflict,
+        sqlx::Error::Database(error) => match error.code().as_deref() {
+            // serialization failure (also MySQL deadlocks) and PostgreSQL deadlocks
+            Some("40001") | Some("40P01") => ErrorKind::Transient,
+            // connection exceptions
+            Some(code) if code.starts_with("08") => ErrorKind::Transient,
+            _ => match error.try_downcast_ref::<MySqlDatabaseError>() {
+                // lock wait timeout and deadlock
+                Some(error) if matches!(error.number(), 1205 | 1213) => ErrorKind::Transient,
+                _ => ErrorKind::Permanent,
+            },
+        },
+        _ => ErrorKind::Permanent,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::repository::error::*;
+
+    #[test]
+    fn dropped_connections_and_pool_timeouts_are_transient() {
+        let dropped = Error::execution(sqlx::Error::Io(io::Error::from(
+            io::ErrorKind::ConnectionReset,
+        )));
+        let timed_out = Error::transaction(sqlx::Error::PoolTimedOut);
+
+        assert_eq!(dropped.kind(), ErrorKind::Transient);
+        assert_eq!(timed_out.kind(), ErrorKind::Transient);
+        assert!(timed_out.is_transient());
+    }
+
+    #[test]
+    fn unclassified_database_errors_are_permanent() {
+        let error = Error::execution(sqlx::Error::RowNotFound);
+
+        assert!(matches!(error, Error::Execution(..)));
+        assert_eq!(error.kind(), ErrorKind::Permanent);
+    }
+
+    #[test]
+    fn commit_failures_are_not_retried() {
+        let error = Error::Commit(Box::new(sqlx::Error::Io(io::Error::from(
+            io::ErrorKind::ConnectionReset,
+        ))));
+
+        assert_eq!(error.kind(), ErrorKind::Permanent);
+    }
+
+    #[test]
+    fn non_database_errors_are_permanent() {
+        assert_eq!(Error::NotFound(Uuid::new_v4()).kind(), ErrorKind::Permanent);
+        assert_eq!(
+            Error::Serialization(Box::new(io::Error::from(io::ErrorKind::InvalidData))).kind(),
+            ErrorKind::Permanent
+        );
+    }
+}
@@ -1,12 +1,123 @@
+use std::collections::HashMap;
+
 use async_trait::async_trait;
 use uuid::Uuid;
 
 use crate::aggregate::EventSourced;
 use crate::envelope::Envelope;
 use crate::repository::error::Error;
+use crate::repository::query::{EventFilter, Page};
 
 #[async_trait]
 pub trait Repository<A: EventSourced>: Clone + Send + Sync {
     async fn save(&mut self, aggregate: &mut A) -> Result<(), Error>;
     async fn find_all_events(&self, aggregate_id: &Uuid) -> Result<Vec<Envelope<A>>, Error>;
+
+    // Finds the streams of many aggregates at once, grouped by aggregate ID. Streams that are
+    // not found, tombstoned or archived are left out instead of failing the whole lookup.
+    // Backends override this to fetch everything in a single round trip.
+    async fn find_many_events(
+        &self,
+        aggregate_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<Envelope<A>>>, Error> {
+        let mut streams = HashMap::new();
+        for aggregate_id in aggregate_ids {
+            match self.find_all_events(aggregate_id).await {
+                Ok(envelopes) => {
+                    streams.insert(*aggregate_id, envelopes);
+                }
+                Err(Error::NotFound(..) | Error::Deleted(..) | Error::Archived(..)) => (),
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(streams)
+    }
+}
+
+// Optional operations ending the life of a stream. Tombstoned streams keep their events but
+// reject further saves and loads with `Error::Deleted`. Archived streams are moved into cold
+// storage and answer with `Error::Archived`, unless they had been tombstoned before. Deleted
+// streams are removed for good and are not found afterwards.
+#[async_trait]
+pub trait StreamLifecycle<A: EventSourced>: Repository<A> {
+    async fn tombstone(&mut self, aggregate_id: &Uuid) -> Result<(), Error>;
+    async fn archive(&mut self, aggregate_id: &Uuid) -> Result<(), Error>;
+    async fn delete(&mut self, aggregate_id: &Uuid) -> Result<(), Error>;
+}
+
+// Optional queries across the streams of an aggregate type, leaving out tombstoned and
+// archived streams. Aggregate IDs are listed in ascending order, and events by creation time.
+#[async_trait]
+pub trait StreamQuery<A: EventSourced>: Repository<A> {
+    async fn list_aggregate_ids(&self, page: Page) -> Result<Vec<Uuid>, Error>;
+    async fn count_streams(&self) -> Result<i64, Error>;
+    async fn find_events(
+        &self,
+        filter: &EventFilter,
+        page: Page,
+    ) -> Result<Vec<Envelope<A>>, Error>;
+}
+
+// an event read in the order of saving, with its global position among the live events
+#[derive(Debug, PartialEq)]
+pub struct RecordedEvent<A: EventSourced> {
+    pub position: i64,
+    pub envelope: Envelope<A>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveOutcome {
+    Saved,
+    // nothing was written, as the key had already been saved with the events of this aggregate
+    Duplicate(Uuid),
+}
+
+// Optional saving with an idempotency key, unique per aggregate type and committed together
+// with the events. Saving again with a used key drains the pending events without writing
+// them and answers with the aggregate the key was first saved for.
+#[async_trait]
+pub trait IdempotentRepository<A: EventSourced>: Repository<A> {
+    async fn save_with_key(
+        &mut self,
+        aggregate: &mut A,
+        idempotency_key: &str,
+    ) -> Result<SaveOutcome, Error>;
+}
+
+// Unique values such as email addresses, held by one aggregate at a time within a scope of its
+// aggregate type
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reservations {
+    pub reserved: Vec<(String, String)>,
+    pub released: Vec<(String, String)>,
+}
+
+impl Reservations {
+    pub fn reserve(mut self, scope: impl Into<String>, key: impl Into<String>) -> Self {
+        self.reserved.push((scope.into(), key.into()));
+        self
+    }
+
+    pub fn release(mut self, scope: impl Into<String>, key: impl Into<String>) -> Self {
+        self.released.push((scope.into(), key.into()));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reserved.is_empty() && self.released.is_empty()
+    }
+}
+
+// Optional saving that releases and then reserves keys, committed together with the events.
+// Reserving a key held by another aggregate fails with `Error::Reserved` and writes nothing,
+// while the holder may reserve its keys again. Releasing only frees keys held by the aggregate,
+// and deleting a stream frees all of them.
+#[async_trait]
+pub trait ReservingRepository<A: EventSourced>: Repository<A> {
+    async fn save_with_reservations(
+        &mut self,
+        aggregate: &mut A,
+        reservations: &Reservations,
+    ) -> Result<(), Error>;
+    async fn find_reservation(&self, scope: &str, key: &str) -> Result<Option<Uuid>, Error>;
 }
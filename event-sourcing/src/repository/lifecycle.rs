@@ -0,0 +1,41 @@
+use uuid::Uuid;
+
+use crate::repository::error::Error;
+
+// Streams without a recorded state are active. Tombstoned streams keep their events in the
+// event table, while archived ones have them moved into the archive table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StreamState {
+    Tombstoned,
+    Archived,
+}
+
+impl StreamState {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            StreamState::Tombstoned => "Tombstoned",
+            StreamState::Archived => "Archived",
+        }
+    }
+
+    pub(crate) fn into_error(self, aggregate_id: Uuid) -> Error {
+        match self {
+            StreamState::Tombstoned => Error::Deleted(aggregate_id),
+            StreamState::Archived => Error::Archived(aggregate_id),
+        }
+    }
+}
+
+impl TryFrom<&str> for StreamState {
+    type Error = Error;
+
+    fn try_from(state: &str) -> Result<Self, Self::Error> {
+        match state {
+            "Tombstoned" => Ok(StreamState::Tombstoned),
+            "Archived" => Ok(StreamState::Archived),
+            _ => Err(Error::Deserialization(
+                format!("Unknown stream state {state}").into(),
+            )),
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, RwLock},
 };
 
@@ -8,13 +8,110 @@ use uuid::Uuid;
 
 use crate::aggregate::EventSourced;
 use crate::envelope::Envelope;
+use crate::instrumentation;
 use crate::repository::error::Error;
-use crate::repository::interface::Repository;
-use crate::repository::serialization::SerializedEnvelope;
+use crate::repository::interface::{
+    IdempotentRepository, Repository, Reservations, ReservingRepository, SaveOutcome,
+    StreamLifecycle, StreamQuery,
+};
+use crate::repository::lifecycle::StreamState;
+use crate::repository::migration::MigrationStore;
+use crate::repository::query::{paginate, EventFilter, Page};
+use crate::repository::serialization::{group_by_aggregate, SerializedEnvelope};
+
+const BACKEND: &str = "memory";
+
+// rows are keyed by tenant, aggregate name and ID, as the unique index of the SQL event tables is
+type StreamKey = (Uuid, String, Uuid);
+// keyed by tenant, aggregate name and idempotency key
+type IdempotencyKey = (Uuid, String, String);
+// keyed by tenant, aggregate name, scope and reserved key
+type ReservedKey = (Uuid, String, String, String);
 
 #[derive(Debug, Clone, Default)]
 pub struct MemoryRepository {
-    rows: Arc<RwLock<HashMap<Uuid, Vec<SerializedEnvelope>>>>,
+    store: Arc<RwLock<Store>>,
+    tenant_id: Uuid,
+}
+
+impl MemoryRepository {
+    // scopes a repository sharing the same store to the given tenant
+    pub fn for_tenant(mut self, tenant_id: Uuid) -> Self {
+        self.tenant_id = tenant_id;
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct Store {
+    rows: HashMap<StreamKey, Vec<SerializedEnvelope>>,
+    archived_rows: HashMap<StreamKey, Vec<SerializedEnvelope>>,
+    states: HashMap<StreamKey, StreamState>,
+    migration_rows: Option<HashMap<StreamKey, Vec<SerializedEnvelope>>>,
+    backup_rows: Option<HashMap<StreamKey, Vec<SerializedEnvelope>>>,
+    idempotency_keys: HashMap<IdempotencyKey, Uuid>,
+    reserved_keys: HashMap<ReservedKey, Uuid>,
+}
+
+impl Store {
+    fn append(&mut self, key: StreamKey, mut events: Vec<SerializedEnvelope>) -> Result<(), Error> {
+        if let Some(state) = self.states.get(&key) {
+            return Err(state.into_error(key.2));
+        }
+
+        let stream = self.rows.entry(key).or_default();
+
+        if let Some(event) = events.iter().find(|event| {
+            stream
+                .iter()
+                .any(|stored| stored.aggregate_sequence == event.aggregate_sequence)
+        }) {
+            return Err(Error::Conflict(
+                format!(
+                    "Sequence {} of aggregate {} already exists",
+                    event.aggregate_sequence, event.aggregate_id
+                )
+                .into(),
+            ));
+        }
+
+        stream.append(&mut events);
+        Ok(())
+    }
+
+    fn check_reservations(
+        &self,
+        stream: &StreamKey,
+        reservations: &Reservations,
+    ) -> Result<(), Error> {
+        for (scope, key) in &reservations.reserved {
+            let reserved_key = (stream.0, stream.1.clone(), scope.clone(), key.clone());
+            match self.reserved_keys.get(&reserved_key) {
+                Some(holder) if *holder != stream.2 => {
+                    return Err(Error::Reserved {
+                        scope: scope.clone(),
+                        key: key.clone(),
+                        aggregate_id: *holder,
+                    })
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_reservations(&mut self, stream: &StreamKey, reservations: &Reservations) {
+        for (scope, key) in &reservations.released {
+            let reserved_key = (stream.0, stream.1.clone(), scope.clone(), key.clone());
+            if self.reserved_keys.get(&reserved_key) == Some(&stream.2) {
+                self.reserved_keys.remove(&reserved_key);
+            }
+        }
+        for (scope, key) in &reservations.reserved {
+            let reserved_key = (stream.0, stream.1.clone(), scope.clone(), key.clone());
+            self.reserved_keys.insert(reserved_key, stream.2);
+        }
+    }
 }
 
 #[async_trait]
@@ -23,45 +120,432 @@ where
     A: EventSourced,
 {
     async fn save(&mut self, aggregate: &mut A) -> Result<(), Error> {
-        let mut store = self.rows.write().map_err(|_| Error::Unknown)?;
+        let pending_events = aggregate.drain_pending_events();
 
-        let mut serialized_events = aggregate
-            .drain_pending_events()
-            .into_iter()
-            .map(|envelope| SerializedEnvelope::try_from(envelope).map_err(|_| Error::Unknown))
-            .collect::<Result<Vec<SerializedEnvelope>, Error>>()?;
+        instrumentation::save::<A, _>(
+            BACKEND,
+            aggregate.get_id(),
+            aggregate.get_sequence(),
+            pending_events.len(),
+            async {
+                let serialized_events = pending_events
+                    .into_iter()
+                    .map(|mut envelope| {
+                        envelope.tenant_id = self.tenant_id;
+                        SerializedEnvelope::try_from(envelope)
+                    })
+                    .collect::<Result<Vec<SerializedEnvelope>, Error>>()?;
 
-        store
-            .entry(aggregate.get_id())
-            .or_insert_with(Vec::new)
-            .append(&mut serialized_events);
+                if serialized_events.is_empty() {
+                    return Ok(());
+                }
 
-        Ok(())
+                let mut store = self.store.write().map_err(|_| Error::Unknown)?;
+                store.append(
+                    (self.tenant_id, A::get_name(), aggregate.get_id()),
+                    serialized_events,
+                )
+            },
+        )
+        .await
     }
 
     async fn find_all_events(&self, aggregate_id: &Uuid) -> Result<Vec<Envelope<A>>, Error> {
-        let store = self.rows.read().map_err(|_| Error::Unknown)?;
+        instrumentation::find_all_events(BACKEND, aggregate_id, async {
+            let store = self.store.read().map_err(|_| Error::Unknown)?;
+            let key = (self.tenant_id, A::get_name(), *aggregate_id);
+
+            if let Some(state) = store.states.get(&key) {
+                return Err(state.into_error(*aggregate_id));
+            }
+
+            match store.rows.get(&key) {
+                Some(events) if !events.is_empty() => events
+                    .iter()
+                    .cloned()
+                    .map(Envelope::try_from)
+                    .collect::<Result<Vec<Envelope<A>>, Error>>(),
+                _ => Err(Error::NotFound(*aggregate_id)),
+            }
+        })
+        .await
+    }
+
+    async fn find_many_events(
+        &self,
+        aggregate_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<Envelope<A>>>, Error> {
+        instrumentation::find_many_events(BACKEND, aggregate_ids.len(), async {
+            let store = self.store.read().map_err(|_| Error::Unknown)?;
+
+            let events = aggregate_ids
+                .iter()
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .map(|aggregate_id| (self.tenant_id, A::get_name(), *aggregate_id))
+                .filter(|key| !store.states.contains_key(key))
+                .filter_map(|key| store.rows.get(&key))
+                .flatten()
+                .cloned()
+                .collect();
+
+            group_by_aggregate(events)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl<A> StreamLifecycle<A> for MemoryRepository
+where
+    A: EventSourced,
+{
+    async fn tombstone(&mut self, aggregate_id: &Uuid) -> Result<(), Error> {
+        let mut store = self.store.write().map_err(|_| Error::Unknown)?;
+        let key = (self.tenant_id, A::get_name(), *aggregate_id);
+
+        match store.states.get(&key) {
+            Some(StreamState::Tombstoned) => Ok(()),
+            Some(StreamState::Archived) => Err(Error::Archived(*aggregate_id)),
+            None if store.rows.contains_key(&key) => {
+                store.states.insert(key, StreamState::Tombstoned);
+                Ok(())
+            }
+            None => Err(Error::NotFound(*aggregate_id)),
+        }
+    }
+
+    async fn archive(&mut self, aggregate_id: &Uuid) -> Result<(), Error> {
+        let mut store = self.store.write().map_err(|_| Error::Unknown)?;
+        let key = (self.tenant_id, A::get_name(), *aggregate_id);
+
+        if store.states.get(&key) == Some(&StreamState::Archived) {
+            return Ok(());
+        }
+
+        match store.rows.remove(&key) {
+            Some(events) => {
+                store.archived_rows.insert(key.clone(), events);
+                store.states.entry(key).or_insert(StreamState::Archived);
+                Ok(())
+            }
+            None => Err(Error::NotFound(*aggregate_id)),
+        }
+    }
+
+    async fn delete(&mut self, aggregate_id: &Uuid) -> Result<(), Error> {
+        let mut store = self.store.write().map_err(|_| Error::Unknown)?;
+        let key = (self.tenant_id, A::get_name(), *aggregate_id);
+
+        let events = store.rows.remove(&key);
+        let archived_events = store.archived_rows.remove(&key);
+        let state = store.states.remove(&key);
+        store
+            .idempotency_keys
+            .retain(|(tenant_id, name, _), aggregate_id| {
+                (*tenant_id, name, *aggregate_id) != (key.0, &key.1, key.2)
+            });
+        store
+            .reserved_keys
+            .retain(|(tenant_id, name, _, _), aggregate_id| {
+                (*tenant_id, name, *aggregate_id) != (key.0, &key.1, key.2)
+            });
+
+        match (events, archived_events, state) {
+            (None, None, None) => Err(Error::NotFound(*aggregate_id)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<A> IdempotentRepository<A> for MemoryRepository
+where
+    A: EventSourced,
+{
+    async fn save_with_key(
+        &mut self,
+        aggregate: &mut A,
+        idempotency_key: &str,
+    ) -> Result<SaveOutcome, Error> {
+        let pending_events = aggregate.drain_pending_events();
 
-        match store.get(aggregate_id) {
-            Some(events) => match events.is_empty() {
-                true => Err(Error::NotFound(*aggregate_id)),
-                false => Ok(events
-                    .clone()
+        instrumentation::save::<A, _>(
+            BACKEND,
+            aggregate.get_id(),
+            aggregate.get_sequence(),
+            pending_events.len(),
+            async {
+                let serialized_events = pending_events
                     .into_iter()
-                    .map(|event| Envelope::try_from(event).map_err(|_| Error::Unknown))
-                    .collect::<Result<Vec<Envelope<A>>, Error>>()?),
+                    .map(|mut envelope| {
+                        envelope.tenant_id = self.tenant_id;
+                        SerializedEnvelope::try_from(envelope)
+                    })
+                    .collect::<Result<Vec<SerializedEnvelope>, Error>>()?;
+
+                if serialized_events.is_empty() {
+                    return Ok(SaveOutcome::Saved);
+                }
+
+                let mut store = self.store.write().map_err(|_| Error::Unknown)?;
+                let key = (self.tenant_id, A::get_name(), idempotency_key.to_string());
+
+                if let Some(aggregate_id) = store.idempotency_keys.get(&key) {
+                    return Ok(SaveOutcome::Duplicate(*aggregate_id));
+                }
+
+                store.append(
+                    (self.tenant_id, A::get_name(), aggregate.get_id()),
+                    serialized_events,
+                )?;
+                store.idempotency_keys.insert(key, aggregate.get_id());
+
+                Ok(SaveOutcome::Saved)
             },
-            None => Err(Error::NotFound(*aggregate_id)),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl<A> ReservingRepository<A> for MemoryRepository
+where
+    A: EventSourced,
+{
+    async fn save_with_reservations(
+        &mut self,
+        aggregate: &mut A,
+        reservations: &Reservations,
+    ) -> Result<(), Error> {
+        let pending_events = aggregate.drain_pending_events();
+
+        instrumentation::save::<A, _>(
+            BACKEND,
+            aggregate.get_id(),
+            aggregate.get_sequence(),
+            pending_events.len(),
+            async {
+                let serialized_events = pending_events
+                    .into_iter()
+                    .map(|mut envelope| {
+                        envelope.tenant_id = self.tenant_id;
+                        SerializedEnvelope::try_from(envelope)
+                    })
+                    .collect::<Result<Vec<SerializedEnvelope>, Error>>()?;
+
+                if serialized_events.is_empty() {
+                    return Ok(());
+                }
+
+                let mut store = self.store.write().map_err(|_| Error::Unknown)?;
+                let stream = (self.tenant_id, A::get_name(), aggregate.get_id());
+
+                // checked up front, so that a rejected save writes nothing
+                store.check_reservations(&stream, reservations)?;
+                store.append(stream.clone(), serialized_events)?;
+                store.apply_reservations(&stream, reservations);
+
+                Ok(())
+            },
+        )
+        .await
+    }
+
+    async fn find_reservation(&self, scope: &str, key: &str) -> Result<Option<Uuid>, Error> {
+        let store = self.store.read().map_err(|_| Error::Unknown)?;
+        let key = (
+            self.tenant_id,
+            A::get_name(),
+            scope.to_string(),
+            key.to_string(),
+        );
+
+        Ok(store.reserved_keys.get(&key).copied())
+    }
+}
+
+#[async_trait]
+impl<A> StreamQuery<A> for MemoryRepository
+where
+    A: EventSourced,
+{
+    async fn list_aggregate_ids(&self, page: Page) -> Result<Vec<Uuid>, Error> {
+        let store = self.store.read().map_err(|_| Error::Unknown)?;
+
+        let mut aggregate_ids = active_streams::<A>(&store, self.tenant_id)
+            .map(|((_, _, id), _)| *id)
+            .collect::<Vec<Uuid>>();
+        aggregate_ids.sort();
+
+        Ok(paginate(aggregate_ids, page))
+    }
+
+    async fn count_streams(&self) -> Result<i64, Error> {
+        let store = self.store.read().map_err(|_| Error::Unknown)?;
+
+        Ok(active_streams::<A>(&store, self.tenant_id).count() as i64)
+    }
+
+    async fn find_events(
+        &self,
+        filter: &EventFilter,
+        page: Page,
+    ) -> Result<Vec<Envelope<A>>, Error> {
+        let store = self.store.read().map_err(|_| Error::Unknown)?;
+
+        let mut events = active_streams::<A>(&store, self.tenant_id)
+            .flat_map(|(_, events)| events)
+            .filter(|event| filter.matches(event))
+            .cloned()
+            .collect::<Vec<SerializedEnvelope>>();
+        events.sort_by_key(|event| {
+            (
+                event.created_at,
+                event.aggregate_id,
+                event.aggregate_sequence,
+            )
+        });
+
+        paginate(events, page)
+            .into_iter()
+            .map(Envelope::try_from)
+            .collect()
+    }
+}
+
+fn active_streams<A: EventSourced>(
+    store: &Store,
+    tenant_id: Uuid,
+) -> impl Iterator<Item = (&StreamKey, &Vec<SerializedEnvelope>)> {
+    let aggregate_name = A::get_name();
+    store.rows.iter().filter(move |(key, events)| {
+        key.0 == tenant_id
+            && key.1 == aggregate_name
+            && !events.is_empty()
+            && !store.states.contains_key(*key)
+    })
+}
+
+#[async_trait]
+impl MigrationStore for MemoryRepository {
+    async fn find_stream_ids(&self, aggregate_name: &str) -> Result<Vec<Uuid>, Error> {
+        let store = self.store.read().map_err(|_| Error::Unknown)?;
+
+        Ok(store
+            .rows
+            .keys()
+            .filter(|(tenant_id, name, _)| *tenant_id == self.tenant_id && name == aggregate_name)
+            .map(|(_, _, id)| *id)
+            .collect())
+    }
+
+    async fn find_stream(
+        &self,
+        aggregate_name: &str,
+        aggregate_id: &Uuid,
+    ) -> Result<Vec<SerializedEnvelope>, Error> {
+        let store = self.store.read().map_err(|_| Error::Unknown)?;
+
+        Ok(store
+            .rows
+            .get(&(self.tenant_id, aggregate_name.to_string(), *aggregate_id))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn count_events(&self) -> Result<i64, Error> {
+        let store = self.store.read().map_err(|_| Error::Unknown)?;
+
+        Ok(count_rows(&store.rows))
+    }
+
+    async fn create_copy(&self, aggregate_name: &str) -> Result<(), Error> {
+        let mut store = self.store.write().map_err(|_| Error::Unknown)?;
+
+        let rows = store
+            .rows
+            .iter()
+            .filter(|((tenant_id, name, _), _)| {
+                *tenant_id != self.tenant_id || name != aggregate_name
+            })
+            .map(|(key, events)| (key.clone(), events.clone()))
+            .collect();
+        store.migration_rows = Some(rows);
+        Ok(())
+    }
+
+    async fn insert_copy(&self, events: Vec<SerializedEnvelope>) -> Result<(), Error> {
+        let mut store = self.store.write().map_err(|_| Error::Unknown)?;
+        let rows = store.migration_rows.as_mut().ok_or(Error::Unknown)?;
+
+        for event in events {
+            rows.entry((
+                event.tenant_id,
+                event.aggregate_name.clone(),
+                event.aggregate_id,
+            ))
+            .or_default()
+            .push(event);
+        }
+        Ok(())
+    }
+
+    async fn count_copy(&self) -> Result<i64, Error> {
+        let store = self.store.read().map_err(|_| Error::Unknown)?;
+
+        store
+            .migration_rows
+            .as_ref()
+            .map(count_rows)
+            .ok_or(Error::Unknown)
+    }
+
+    async fn swap_copy(&self, expected_events: i64) -> Result<(), Error> {
+        let mut store = self.store.write().map_err(|_| Error::Unknown)?;
+
+        let actual_events = count_rows(&store.rows);
+        if actual_events != expected_events {
+            return Err(Error::Conflict(
+                format!("{actual_events} events found while {expected_events} were expected")
+                    .into(),
+            ));
         }
+
+        let rows = store.migration_rows.take().ok_or(Error::Unknown)?;
+        let backup_rows = std::mem::replace(&mut store.rows, rows);
+        store.backup_rows = Some(backup_rows);
+        Ok(())
+    }
+
+    async fn drop_copy(&self) -> Result<(), Error> {
+        let mut store = self.store.write().map_err(|_| Error::Unknown)?;
+
+        store.migration_rows = None;
+        Ok(())
+    }
+
+    async fn drop_backup(&self) -> Result<(), Error> {
+        let mut store = self.store.write().map_err(|_| Error::Unknown)?;
+
+        store.backup_rows = None;
+        Ok(())
     }
 }
 
+fn count_rows(rows: &HashMap<StreamKey, Vec<SerializedEnvelope>>) -> i64 {
+    rows.values().map(|events| events.len() as i64).sum()
+}
+
 #[cfg(test)]
 mod test {
+    use std::time::SystemTime;
+
     use uuid::Uuid;
 
     use crate::aggregate::*;
     use crate::event::*;
+    use crate::repository::conformance;
     use crate::repository::memory::*;
     use crate::test::*;
 
@@ -150,4 +634,60 @@ mod test {
         assert_eq!(event_1.get_name(), "UserRegistered");
         assert_eq!(event_2.get_name(), "UserModified");
     }
+
+    #[tokio::test]
+    async fn memory_repository_conforms_to_repository_behaviours() {
+        conformance::run_all(MemoryRepository::default()).await;
+    }
+
+    #[tokio::test]
+    async fn memory_repository_conforms_to_stream_lifecycle_behaviours() {
+        conformance::run_lifecycle(MemoryRepository::default()).await;
+    }
+
+    #[tokio::test]
+    async fn repository_reports_deserialization_error_for_malformed_rows() {
+        let id = Uuid::new_v4();
+        let repository = MemoryRepository::default();
+        repository.store.write().unwrap().rows.insert(
+            (Uuid::nil(), User::get_name(), id),
+            vec![SerializedEnvelope {
+                id: Uuid::new_v4(),
+                tenant_id: Uuid::nil(),
+                aggregate_name: User::get_name(),
+                aggregate_id: id,
+                aggregate_sequence: 1,
+                event_name: String::from("UserRegistered"),
+                event_version: String::from("1.0.0"),
+                event_payload: serde_json::json!({ "UserRenamed": {} }),
+                metadata: serde_json::json!({}),
+                created_at: SystemTime::now(),
+            }],
+        );
+
+        let result: Result<Vec<Envelope<User>>, Error> = repository.find_all_events(&id).await;
+
+        assert!(matches!(result, Err(Error::Deserialization(..))));
+    }
+
+    #[tokio::test]
+    async fn memory_repository_conforms_to_stream_query_behaviours() {
+        conformance::run_query(MemoryRepository::default()).await;
+    }
+
+    #[tokio::test]
+    async fn memory_repository_conforms_to_tenancy_behaviours() {
+        let repository = MemoryRepository::default();
+        conformance::run_tenancy(|tenant_id| repository.clone().for_tenant(tenant_id)).await;
+    }
+
+    #[tokio::test]
+    async fn memory_repository_conforms_to_idempotency_behaviours() {
+        conformance::run_idempotency(MemoryRepository::default()).await;
+    }
+
+    #[tokio::test]
+    async fn memory_repository_conforms_to_reservation_behaviours() {
+        conformance::run_reservation(MemoryRepository::default()).await;
+    }
 }
@@ -0,0 +1,487 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::repository::error::Error;
+use crate::repository::serialization::SerializedEnvelope;
+
+// Storage operations needed by the offline migration runner. The copy holds every live event
+// except the ones of the migrated aggregate type, which are written back after transforming.
+// Streams are read from the tenant the store is scoped to, and other tenants are copied as-is.
+#[async_trait]
+pub trait MigrationStore: Send + Sync {
+    async fn find_stream_ids(&self, aggregate_name: &str) -> Result<Vec<Uuid>, Error>;
+    async fn find_stream(
+        &self,
+        aggregate_name: &str,
+        aggregate_id: &Uuid,
+    ) -> Result<Vec<SerializedEnvelope>, Error>;
+    async fn count_events(&self) -> Result<i64, Error>;
+
+    async fn create_copy(&self, aggregate_name: &str) -> Result<(), Error>;
+    async fn insert_copy(&self, events: Vec<SerializedEnvelope>) -> Result<(), Error>;
+    async fn count_copy(&self) -> Result<i64, Error>;
+    // Replaces the live events with the copy in one step and keeps the replaced ones as a
+    // backup, dropping the backup of any earlier migration. Fails with a conflict when the live
+    // events no longer number `expected_events`.
+    async fn swap_copy(&self, expected_events: i64) -> Result<(), Error>;
+    async fn drop_copy(&self) -> Result<(), Error>;
+    // Drops the events replaced by the last swap, once the migration has been verified
+    async fn drop_backup(&self) -> Result<(), Error>;
+}
+
+// Rewrites the events of a single stream, given in sequence order
+pub trait Transform: Send + Sync {
+    fn apply(&self, events: Vec<SerializedEnvelope>) -> Result<Vec<SerializedEnvelope>, Error>;
+}
+
+impl<F> Transform for F
+where
+    F: Fn(Vec<SerializedEnvelope>) -> Result<Vec<SerializedEnvelope>, Error> + Send + Sync,
+{
+    fn apply(&self, events: Vec<SerializedEnvelope>) -> Result<Vec<SerializedEnvelope>, Error> {
+        self(events)
+    }
+}
+
+// Renames events, including the variant tag of externally tagged payloads
+pub struct RenameEvent {
+    event_name: String,
+    new_event_name: String,
+    new_event_version: Option<String>,
+}
+
+impl RenameEvent {
+    pub fn new(event_name: impl Into<String>, new_event_name: impl Into<String>) -> Self {
+        Self {
+            event_name: event_name.into(),
+            new_event_name: new_event_name.into(),
+            new_event_version: None,
+        }
+    }
+
+    pub fn with_version(mut self, new_event_version: impl Into<String>) -> Self {
+        self.new_event_version = Some(new_event_version.into());
+        self
+    }
+}
+
+impl Transform for RenameEvent {
+    fn apply(&self, events: Vec<SerializedEnvelope>) -> Result<Vec<SerializedEnvelope>, Error> {
+        Ok(events
+            .into_iter()
+            .map(|mut event| {
+                if event.event_name != self.event_name {
+                    return event;
+                }
+
+                if let Value::Object(payload) = &mut event.event_payload {
+                    if payload.len() == 1 {
+                        if let Some(fields) = payload.remove(&self.event_name) {
+                            payload.insert(self.new_event_name.clone(), fields);
+                        }
+                    }
+                }
+                event.event_name = self.new_event_name.clone();
+                if let Some(version) = &self.new_event_version {
+                    event.event_version = version.clone();
+                }
+                event
+            })
+            .collect())
+    }
+}
+
+pub struct DropEvent {
+    event_name: String,
+}
+
+impl DropEvent {
+    pub fn new(event_name: impl Into<String>) -> Self {
+        Self {
+            event_name: event_name.into(),
+        }
+    }
+}
+
+impl Transform for DropEvent {
+    fn apply(&self, events: Vec<SerializedEnvelope>) -> Result<Vec<SerializedEnvelope>, Error> {
+        Ok(events
+            .into_iter()
+            .filter(|event| event.event_name != self.event_name)
+            .collect())
+    }
+}
+
+// Replaces each event with the given name by the events returned from `split`
+pub struct SplitEvent<F> {
+    event_name: String,
+    split: F,
+}
+
+impl<F> SplitEvent<F>
+where
+    F: Fn(SerializedEnvelope) -> Result<Vec<SerializedEnvelope>, Error> + Send + Sync,
+{
+    pub fn new(event_name: impl Into<String>, split: F) -> Self {
+        Self {
+            event_name: event_name.into(),
+            split,
+        }
+    }
+}
+
+impl<F> Transform for SplitEvent<F>
+where
+    F: Fn(SerializedEnvelope) -> Result<Vec<SerializedEnvelope>, Error> + Send + Sync,
+{
+    fn apply(&self, events: Vec<SerializedEnvelope>) -> Result<Vec<SerializedEnvelope>, Error> {
+        let mut split_events = Vec::with_capacity(events.len());
+        for event in events {
+            match event.event_name == self.event_name {
+                true => split_events.extend((self.split)(event)?),
+                false => split_events.push(event),
+            }
+        }
+        Ok(split_events)
+    }
+}
+
+// Replaces each run of consecutive events with one of the given names by the event returned
+// from `merge`
+pub struct MergeEvents<F> {
+    event_names: Vec<String>,
+    merge: F,
+}
+
+impl<F> MergeEvents<F>
+where
+    F: Fn(Vec<SerializedEnvelope>) -> Result<SerializedEnvelope, Error> + Send + Sync,
+{
+    pub fn new(event_names: &[&str], merge: F) -> Self {
+        Self {
+            event_names: event_names.iter().map(|name| name.to_string()).collect(),
+            merge,
+        }
+    }
+}
+
+impl<F> Transform for MergeEvents<F>
+where
+    F: Fn(Vec<SerializedEnvelope>) -> Result<SerializedEnvelope, Error> + Send + Sync,
+{
+    fn apply(&self, events: Vec<SerializedEnvelope>) -> Result<Vec<SerializedEnvelope>, Error> {
+        let mut merged_events = Vec::with_capacity(events.len());
+        let mut run = Vec::new();
+        for event in events {
+            if self.event_names.contains(&event.event_name) {
+                run.push(event);
+                continue;
+            }
+            if !run.is_empty() {
+                merged_events.push((self.merge)(std::mem::take(&mut run))?);
+            }
+            merged_events.push(event);
+        }
+        if !run.is_empty() {
+            merged_events.push((self.merge)(run)?);
+        }
+        Ok(merged_events)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    #[error("{0}")]
+    Repository(#[from] Error),
+
+    #[error("Failed to transform stream {aggregate_id}: {source}")]
+    Transform { aggregate_id: Uuid, source: Error },
+
+    #[error("Copied {actual} events while {expected} were expected")]
+    CountMismatch { expected: i64, actual: i64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamChange {
+    pub aggregate_id: Uuid,
+    pub events_before: usize,
+    pub events_after: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MigrationReport {
+    pub streams_read: usize,
+    pub events_read: usize,
+    pub events_written: usize,
+    pub changes: Vec<StreamChange>,
+}
+
+// Offline migration of every stream of one aggregate type. Writers have to be stopped while it
+// runs, and the swap is refused when the number of live events changed in the meantime.
+pub struct Migration {
+    aggregate_name: String,
+    transforms: Vec<Box<dyn Transform>>,
+}
+
+impl Migration {
+    pub fn new(aggregate_name: impl Into<String>) -> Self {
+        Self {
+            aggregate_name: aggregate_name.into(),
+            transforms: Vec::new(),
+        }
+    }
+
+    pub fn with_transform(mut self, transform: impl Transform + 'static) -> Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    // Transforms every stream and reports the outcome without writing anything
+    pub async fn dry_run<S: MigrationStore>(
+        &self,
+        store: &S,
+    ) -> Result<MigrationReport, MigrationError> {
+        self.migrate(store, false).await
+    }
+
+    pub async fn run<S: MigrationStore>(
+        &self,
+        store: &S,
+    ) -> Result<MigrationReport, MigrationError> {
+        let total_events = store.count_events().await?;
+
+        store.create_copy(&self.aggregate_name).await?;
+        let result = self.copy_and_swap(store, total_events).await;
+        if result.is_err() {
+            store.drop_copy().await?;
+        }
+        result
+    }
+
+    async fn copy_and_swap<S: MigrationStore>(
+        &self,
+        store: &S,
+        total_events: i64,
+    ) -> Result<MigrationReport, MigrationError> {
+        let report = self.migrate(store, true).await?;
+
+        let expected = total_events - report.events_read as i64 + report.events_written as i64;
+        let actual = store.count_copy().await?;
+        if expected != actual {
+            return Err(MigrationError::CountMismatch { expected, actual });
+        }
+
+        store.swap_copy(total_events).await?;
+        Ok(report)
+    }
+
+    async fn migrate<S: MigrationStore>(
+        &self,
+        store: &S,
+        write: bool,
+    ) -> Result<MigrationReport, MigrationError> {
+        let mut report = MigrationReport::default();
+
+        for aggregate_id in store.find_stream_ids(&self.aggregate_name).await? {
+            let events = store
+                .find_stream(&self.aggregate_name, &aggregate_id)
+                .await?;
+            let migrated_events =
+                self.transform(&aggregate_id, events.clone())
+                    .map_err(|source| MigrationError::Transform {
+                        aggregate_id,
+                        source,
+                    })?;
+
+            report.streams_read += 1;
+            report.events_read += events.len();
+            report.events_written += migrated_events.len();
+            if migrated_events != events {
+                report.changes.push(StreamChange {
+                    aggregate_id,
+                    events_before: events.len(),
+                    events_after: migrated_events.len(),
+                });
+            }
+
+            if write && !migrated_events.is_empty() {
+                store.insert_copy(migrated_events).await?;
+            }
+        }
+
+        Ok(report)
+    }
+
+    // Sequences are renumbered from 1, as events may have been added or removed
+    fn transform(
+        &self,
+        aggregate_id: &Uuid,
+        events: Vec<SerializedEnvelope>,
+    ) -> Result<Vec<SerializedEnvelope>, Error> {
+        let events = self
+            .transforms
+            .iter()
+            .try_fold(events, |events, transform| transform.apply(events))?;
+
+        Ok(events
+            .into_iter()
+            .zip(1..)
+            .map(|(event, sequence)| SerializedEnvelope {
+                aggregate_name: self.aggregate_name.clone(),
+                aggregate_id: *aggregate_id,
+                aggregate_sequence: sequence,
+                ..event
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::aggregate::EventSourced;
+    use crate::repository::conformance::{Order, OrderEvent, Shipment};
+    use crate::repository::error::Error;
+    use crate::repository::interface::Repository;
+    use crate::repository::memory::MemoryRepository;
+    use crate::repository::migration::*;
+
+    async fn placed_and_amended_order(repository: &mut MemoryRepository) -> Order {
+        let mut order = Order::default();
+        order.place(Uuid::new_v4(), "book").await;
+        order.amend(2, None).await;
+        order.amend(3, Some("gift")).await;
+        repository.save(&mut order).await.unwrap();
+        order
+    }
+
+    fn migration() -> Migration {
+        Migration::new(Order::get_name())
+            .with_transform(DropEvent::new("OrderAmended"))
+            .with_transform(RenameEvent::new("OrderPlaced", "OrderOpened").with_version("2"))
+    }
+
+    #[tokio::test]
+    async fn dry_run_reports_changes_without_writing() {
+        let mut repository = MemoryRepository::default();
+        let order = placed_and_amended_order(&mut repository).await;
+
+        let report = migration().dry_run(&repository).await.unwrap();
+
+        assert_eq!(report.streams_read, 1);
+        assert_eq!(report.events_read, 3);
+        assert_eq!(report.events_written, 1);
+        assert_eq!(
+            report.changes,
+            vec![StreamChange {
+                aggregate_id: order.get_id(),
+                events_before: 3,
+                events_after: 1,
+            }]
+        );
+        assert_eq!(
+            Repository::<Order>::find_all_events(&repository, &order.get_id())
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn migration_rewrites_streams_and_keeps_other_aggregates() {
+        let mut repository = MemoryRepository::default();
+        let order = placed_and_amended_order(&mut repository).await;
+        let mut shipment = Shipment::default();
+        shipment.dispatch(Uuid::new_v4(), "Seoul").await;
+        repository.save(&mut shipment).await.unwrap();
+
+        migration().run(&repository).await.unwrap();
+
+        let events = repository
+            .find_stream(&Order::get_name(), &order.get_id())
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_name, "OrderOpened");
+        assert_eq!(events[0].event_version, "2");
+        assert_eq!(events[0].aggregate_sequence, 1);
+        assert!(events[0].event_payload.get("OrderOpened").is_some());
+        assert!(
+            Repository::<Shipment>::find_all_events(&repository, &shipment.get_id())
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn migrations_can_run_again_after_a_swap() {
+        let mut repository = MemoryRepository::default();
+        let order = placed_and_amended_order(&mut repository).await;
+
+        migration().run(&repository).await.unwrap();
+        let report = Migration::new(Order::get_name())
+            .with_transform(RenameEvent::new("OrderOpened", "OrderCreated"))
+            .run(&repository)
+            .await
+            .unwrap();
+        repository.drop_backup().await.unwrap();
+
+        assert_eq!(report.changes.len(), 1);
+        let events = repository
+            .find_stream(&Order::get_name(), &order.get_id())
+            .await
+            .unwrap();
+        assert_eq!(events[0].event_name, "OrderCreated");
+    }
+
+    #[tokio::test]
+    async fn split_and_merged_events_are_renumbered() {
+        let mut repository = MemoryRepository::default();
+        let order = placed_and_amended_order(&mut repository).await;
+
+        Migration::new(Order::get_name())
+            .with_transform(MergeEvents::new(&["OrderAmended"], |events| {
+                Ok(events.into_iter().last().unwrap())
+            }))
+            .with_transform(SplitEvent::new("OrderPlaced", |event| {
+                let note = event.with_event("OrderNoted", "1", json!({"OrderNoted": {}}));
+                Ok(vec![event, note])
+            }))
+            .run(&repository)
+            .await
+            .unwrap();
+
+        let events = repository
+            .find_stream(&Order::get_name(), &order.get_id())
+            .await
+            .unwrap();
+        let names = events
+            .iter()
+            .map(|event| (event.aggregate_sequence, event.event_name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![(1, "OrderPlaced"), (2, "OrderNoted"), (3, "OrderAmended")]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_transforms_leave_events_untouched() {
+        let mut repository = MemoryRepository::default();
+        let order = placed_and_amended_order(&mut repository).await;
+
+        let result = Migration::new(Order::get_name())
+            .with_transform(|_| Err(Error::Serialization("broken".into())))
+            .run(&repository)
+            .await;
+
+        assert!(matches!(result, Err(MigrationError::Transform { .. })));
+        let events = Repository::<Order>::find_all_events(&repository, &order.get_id())
+            .await
+            .unwrap();
+        assert!(matches!(events[2].event, OrderEvent::OrderAmended { .. }));
+    }
+}
@@ -1,6 +1,14 @@
+#[cfg(any(test, feature = "conformance"))]
+pub mod conformance;
 pub mod error;
 pub mod interface;
+mod lifecycle;
 pub mod memory;
+pub mod migration;
 pub mod mysql;
 pub mod postgresql;
-mod serialization;
+pub mod query;
+#[cfg(feature = "redb")]
+pub mod redb;
+pub mod retry;
+pub mod serialization;
@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use sqlx::postgres::{PgListener, PgRow};
+use sqlx::{Pool, Postgres, Row};
+use uuid::Uuid;
+
+use crate::aggregate::EventSourced;
+use crate::envelope::Envelope;
+use crate::repository::error::Error;
+use crate::repository::interface::RecordedEvent;
+use crate::repository::postgresql::{read_serialized_envelope, set_tenant, DEFAULT_EVENT_TABLE};
+
+const DEFAULT_BATCH_SIZE: i64 = 100;
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+pub struct Subscription<A: EventSourced> {
+    pool: Pool<Postgres>,
+    listener: PgListener,
+    tenant_id: Uuid,
+    row_level_security: bool,
+    position: i64,
+    buffer: VecDeque<RecordedEvent<A>>,
+    batch_size: i64,
+    poll_interval: Duration,
+}
+
+impl<A: EventSourced> Subscription<A> {
+    pub(super) async fn start(
+        pool: Pool<Postgres>,
+        channel: &str,
+        tenant_id: Uuid,
+        row_level_security: bool,
+        after_position: i64,
+    ) -> Result<Self, Error> {
+        // listen before catching up, so that nothing committed in between is missed
+        let mut listener = PgListener::connect_with(&pool)
+            .await
+            .map_err(|error| Error::Connection(Box::new(error)))?;
+        listener
+            .listen(channel)
+            .await
+            .map_err(|error| Error::Connection(Box::new(error)))?;
+
+        Ok(Self {
+            pool,
+            listener,
+            tenant_id,
+            row_level_security,
+            position: after_position,
+            buffer: VecDeque::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        })
+    }
+
+    pub fn with_batch_size(mut self, batch_size: i64) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    // global position of the last event returned by next
+    pub fn get_position(&self) -> i64 {
+        self.position
+    }
+
+    pub async fn next(&mut self) -> Result<RecordedEvent<A>, Error> {
+        loop {
+            if let Some(event) = self.buffer.pop_front() {
+                self.position = event.position;
+                return Ok(event);
+            }
+
+            if self.fetch().await? == 0 {
+                self.wait().await?;
+            }
+        }
+    }
+
+    // Rows written by transactions that are still in flight may get lower positions than rows
+    // already committed, so only rows older than every running transaction are read.
+    // Otherwise those in-flight rows would be skipped once the position moves past them.
+    async fn fetch(&mut self) -> Result<usize, Error> {
+        let query = format!("SELECT position, tenant_id, id, aggregate_name, aggregate_id, aggregate_sequence, event_name, event_version, event_payload, metadata, (EXTRACT(EPOCH FROM created_at) * 1000)::BIGINT AS created_at FROM {DEFAULT_EVENT_TABLE} WHERE tenant_id = $1 AND aggregate_name = $2 AND position > $3 AND transaction_id < pg_snapshot_xmin(pg_current_snapshot()) ORDER BY position ASC LIMIT $4");
+
+        let mut tx = self.pool.begin().await.map_err(Error::transaction)?;
+        if self.row_level_security {
+            set_tenant(&mut tx, &self.tenant_id).await?;
+        }
+
+        let rows = sqlx::query(&query)
+            .bind(self.tenant_id)
+            .bind(A::get_name())
+            .bind(self.position)
+            .bind(self.batch_size)
+            .map(|row: PgRow| {
+                (
+                    row.get::<i64, _>("position"),
+                    read_serialized_envelope(&row),
+                )
+            })
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(Error::execution)?;
+
+        let count = rows.len();
+        for (position, event) in rows {
+            self.buffer.push_back(RecordedEvent {
+                position,
+                envelope: Envelope::try_from(event)?,
+            });
+        }
+
+        Ok(count)
+    }
+
+    // A notification only tells that something was committed, and the events are read
+    // again from the table. Polling covers notifications lost while reconnecting.
+    async fn wait(&mut self) -> Result<(), Error> {
+        match tokio::time::timeout(self.poll_interval, self.listener.recv()).await {
+            Ok(Ok(_)) | Err(_) => Ok(()),
+            Ok(Err(error)) => Err(Error::Connection(Box::new(error))),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use serde_json::Value;
+
+use crate::repository::serialization::SerializedEnvelope;
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub offset: i64,
+    pub limit: i64,
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            limit: DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+impl Page {
+    pub fn new(offset: i64, limit: i64) -> Self {
+        Self {
+            offset: offset.max(0),
+            limit: limit.max(0),
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        Self::new(self.offset + self.limit, self.limit)
+    }
+}
+
+// Conditions on events of one aggregate type, where every unset condition matches. The
+// creation time range includes its start and excludes its end, and events match the metadata
+// when theirs has every given entry.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventFilter {
+    pub event_name: Option<String>,
+    pub event_version: Option<String>,
+    pub created_from: Option<SystemTime>,
+    pub created_until: Option<SystemTime>,
+    pub metadata: HashMap<String, String>,
+}
+
+impl EventFilter {
+    pub fn with_event_name(mut self, event_name: impl Into<String>) -> Self {
+        self.event_name = Some(event_name.into());
+        self
+    }
+
+    pub fn with_event_version(mut self, event_version: impl Into<String>) -> Self {
+        self.event_version = Some(event_version.into());
+        self
+    }
+
+    pub fn with_created_from(mut self, created_from: SystemTime) -> Self {
+        self.created_from = Some(created_from);
+        self
+    }
+
+    pub fn with_created_until(mut self, created_until: SystemTime) -> Self {
+        self.created_until = Some(created_until);
+        self
+    }
+
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    // the metadata entries as stored, to be matched by JSON containment
+    pub(crate) fn metadata_value(&self) -> Value {
+        Value::Object(
+            self.metadata
+                .iter()
+                .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn matches(&self, event: &SerializedEnvelope) -> bool {
+        self.event_name
+            .as_ref()
+            .is_none_or(|name| *name == event.event_name)
+            && self
+                .event_version
+                .as_ref()
+                .is_none_or(|version| *version == event.event_version)
+            && self
+                .created_from
+                .is_none_or(|from| event.created_at >= from)
+            && self
+                .created_until
+                .is_none_or(|until| event.created_at < until)
+            && self.metadata.iter().all(|(key, value)| {
+                event.metadata.get(key).and_then(Value::as_str) == Some(value.as_str())
+            })
+    }
+}
+
+// pages through items the store could not page through itself
+pub(crate) fn paginate<T>(items: Vec<T>, page: Page) -> Vec<T> {
+    items
+        .into_iter()
+        .skip(page.offset as usize)
+        .take(page.limit as usize)
+        .collect()
+}
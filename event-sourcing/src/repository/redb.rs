@@ -0,0 +1,939 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+use ::redb::{Database, ReadableTable, TableDefinition};
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::aggregate::EventSourced;
+use crate::envelope::Envelope;
+use crate::instrumentation;
+use crate::repository::error::Error;
+use crate::repository::interface::{
+    IdempotentRepository, RecordedEvent, Repository, Reservations, ReservingRepository,
+    SaveOutcome, StreamLifecycle, StreamQuery,
+};
+use crate::repository::lifecycle::StreamState;
+use crate::repository::query::{paginate, EventFilter, Page};
+use crate::repository::serialization::{group_by_aggregate, SerializedEnvelope};
+
+const BACKEND: &str = "redb";
+
+// Events are keyed by tenant, aggregate name, aggregate ID and sequence, so that every stream is
+// a range of ordered keys, and hold their global position along with the event as JSON
+type EventKey<'a> = (u128, &'a str, u128, i64);
+type StoredEvent<'a> = (i64, &'a [u8]);
+// keyed by tenant, aggregate name and ID, as the unique index of the SQL event tables is
+type StreamKey<'a> = (u128, &'a str, u128);
+// keyed by tenant, aggregate name, scope and reserved key
+type ReservedKey<'a> = (u128, &'a str, &'a str, &'a str);
+
+const EVENTS: TableDefinition<EventKey, StoredEvent> = TableDefinition::new("events");
+const ARCHIVED_EVENTS: TableDefinition<EventKey, StoredEvent> =
+    TableDefinition::new("archived_events");
+// global positions of live events, in the order they were saved
+const POSITIONS: TableDefinition<i64, EventKey> = TableDefinition::new("positions");
+const STREAM_STATES: TableDefinition<StreamKey, &str> = TableDefinition::new("stream_states");
+const IDEMPOTENCY_KEYS: TableDefinition<(u128, &str, &str), u128> =
+    TableDefinition::new("idempotency_keys");
+const RESERVED_KEYS: TableDefinition<ReservedKey, u128> = TableDefinition::new("reserved_keys");
+// last values handed out, which are never reused even when the events are archived or deleted
+const SEQUENCES: TableDefinition<&str, i64> = TableDefinition::new("sequences");
+const POSITION_SEQUENCE: &str = "position";
+
+// An event store in a single file for deployments without a database server. Every save is
+// one write transaction, and write transactions run one at a time.
+#[derive(Debug, Clone)]
+pub struct RedbRepository {
+    database: Arc<Database>,
+    tenant_id: Uuid,
+}
+
+impl RedbRepository {
+    // creates the tables of the repository unless they already exist
+    pub fn new(database: Database) -> Result<Self, Error> {
+        let tx = database.begin_write().map_err(transaction)?;
+        tx.open_table(EVENTS).map_err(execution)?;
+        tx.open_table(ARCHIVED_EVENTS).map_err(execution)?;
+        tx.open_table(POSITIONS).map_err(execution)?;
+        tx.open_table(STREAM_STATES).map_err(execution)?;
+        tx.open_table(IDEMPOTENCY_KEYS).map_err(execution)?;
+        tx.open_table(RESERVED_KEYS).map_err(execution)?;
+        tx.open_table(SEQUENCES).map_err(execution)?;
+        tx.commit().map_err(transaction)?;
+
+        Ok(Self {
+            database: Arc::new(database),
+            tenant_id: Uuid::nil(),
+        })
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::new(Database::create(path).map_err(|error| Error::Connection(Box::new(error)))?)
+    }
+
+    // scopes a repository sharing the same database to the given tenant
+    pub fn for_tenant(mut self, tenant_id: Uuid) -> Self {
+        self.tenant_id = tenant_id;
+        self
+    }
+
+    // Reads live events of the aggregate type and tenant saved after the given global position,
+    // in the order they were saved
+    pub async fn find_events_after<A: EventSourced>(
+        &self,
+        after_position: i64,
+        limit: usize,
+    ) -> Result<Vec<RecordedEvent<A>>, Error> {
+        let tenant_id = self.tenant_id.as_u128();
+        let aggregate_name = A::get_name();
+
+        let events = self
+            .run(move |database| {
+                let tx = database.begin_read().map_err(transaction)?;
+                let positions = tx.open_table(POSITIONS).map_err(execution)?;
+                let events = tx.open_table(EVENTS).map_err(execution)?;
+
+                let mut found = Vec::new();
+                for entry in positions.range(after_position + 1..).map_err(execution)? {
+                    if found.len() == limit {
+                        break;
+                    }
+                    let (position, key) = entry.map_err(execution)?;
+                    let key = key.value();
+                    if key.0 != tenant_id || key.1 != aggregate_name {
+                        continue;
+                    }
+                    if let Some(event) = events.get(key).map_err(execution)? {
+                        found.push((position.value(), deserialize(event.value().1)?));
+                    }
+                }
+                Ok(found)
+            })
+            .await?;
+
+        events
+            .into_iter()
+            .map(|(position, event)| {
+                Ok(RecordedEvent {
+                    position,
+                    envelope: Envelope::try_from(event)?,
+                })
+            })
+            .collect()
+    }
+
+    // runs blocking reads and writes of the database off the async runtime
+    async fn run<T, F>(&self, operation: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Database) -> Result<T, Error> + Send + 'static,
+    {
+        let database = self.database.clone();
+        tokio::task::spawn_blocking(move || operation(&database))
+            .await
+            .map_err(|error| Error::Execution(Box::new(error)))?
+    }
+
+    async fn insert_events(
+        &self,
+        aggregate_name: String,
+        aggregate_id: Uuid,
+        events: Vec<SerializedEnvelope>,
+        idempotency_key: Option<String>,
+        reservations: Reservations,
+    ) -> Result<SaveOutcome, Error> {
+        let tenant_id = self.tenant_id.as_u128();
+
+        self.run(move |database| {
+            let tx = database.begin_write().map_err(transaction)?;
+            {
+                let stream = (tenant_id, aggregate_name.as_str(), aggregate_id.as_u128());
+                if let Some(state) =
+                    select_stream_state(&tx.open_table(STREAM_STATES).map_err(execution)?, stream)?
+                {
+                    return Err(state.into_error(aggregate_id));
+                }
+
+                if let Some(idempotency_key) = &idempotency_key {
+                    let mut keys = tx.open_table(IDEMPOTENCY_KEYS).map_err(execution)?;
+                    let key = (tenant_id, aggregate_name.as_str(), idempotency_key.as_str());
+                    if let Some(saved_aggregate_id) = keys.get(key).map_err(execution)? {
+                        return Ok(SaveOutcome::Duplicate(Uuid::from_u128(
+                            saved_aggregate_id.value(),
+                        )));
+                    }
+                    keys.insert(key, aggregate_id.as_u128())
+                        .map_err(execution)?;
+                }
+
+                // an error from here on drops the transaction along with the reservations
+                let mut reserved_keys = tx.open_table(RESERVED_KEYS).map_err(execution)?;
+                for (scope, key) in &reservations.released {
+                    let key = (
+                        tenant_id,
+                        aggregate_name.as_str(),
+                        scope.as_str(),
+                        key.as_str(),
+                    );
+                    let held = reserved_keys
+                        .get(key)
+                        .map_err(execution)?
+                        .map(|holder| holder.value());
+                    if held == Some(stream.2) {
+                        reserved_keys.remove(key).map_err(execution)?;
+                    }
+                }
+                for (scope, key) in &reservations.reserved {
+                    let reserved_key = (
+                        tenant_id,
+                        aggregate_name.as_str(),
+                        scope.as_str(),
+                        key.as_str(),
+                    );
+                    let held = reserved_keys
+                        .get(reserved_key)
+                        .map_err(execution)?
+                        .map(|holder| holder.value());
+                    match held {
+                        Some(holder) if holder != stream.2 => {
+                            return Err(Error::Reserved {
+                                scope: scope.clone(),
+                                key: key.clone(),
+                                aggregate_id: Uuid::from_u128(holder),
+                            })
+                        }
+                        Some(_) => (),
+                        None => {
+                            reserved_keys
+                                .insert(reserved_key, stream.2)
+                                .map_err(execution)?;
+                        }
+                    }
+                }
+
+                let mut table = tx.open_table(EVENTS).map_err(execution)?;
+                let mut positions = tx.open_table(POSITIONS).map_err(execution)?;
+                let mut sequences = tx.open_table(SEQUENCES).map_err(execution)?;
+                let mut position = sequences
+                    .get(POSITION_SEQUENCE)
+                    .map_err(execution)?
+                    .map(|position| position.value())
+                    .unwrap_or(0);
+
+                for event in events {
+                    let key = (stream.0, stream.1, stream.2, event.aggregate_sequence);
+                    if table.get(key).map_err(execution)?.is_some() {
+                        return Err(Error::Conflict(
+                            format!(
+                                "Sequence {} of aggregate {} already exists",
+                                event.aggregate_sequence, event.aggregate_id
+                            )
+                            .into(),
+                        ));
+                    }
+
+                    position += 1;
+                    table
+                        .insert(key, (position, serialize(&event)?.as_slice()))
+                        .map_err(execution)?;
+                    positions.insert(position, key).map_err(execution)?;
+                }
+
+                sequences
+                    .insert(POSITION_SEQUENCE, position)
+                    .map_err(execution)?;
+            }
+            tx.commit().map_err(transaction)?;
+
+            Ok(SaveOutcome::Saved)
+        })
+        .await
+    }
+
+    async fn select_events(
+        &self,
+        aggregate_name: String,
+        aggregate_id: Uuid,
+    ) -> Result<Vec<SerializedEnvelope>, Error> {
+        let tenant_id = self.tenant_id.as_u128();
+
+        self.run(move |database| {
+            let tx = database.begin_read().map_err(transaction)?;
+            let stream = (tenant_id, aggregate_name.as_str(), aggregate_id.as_u128());
+
+            if let Some(state) =
+                select_stream_state(&tx.open_table(STREAM_STATES).map_err(execution)?, stream)?
+            {
+                return Err(state.into_error(aggregate_id));
+            }
+
+            let events = select_stream(&tx.open_table(EVENTS).map_err(execution)?, stream)?;
+            if events.is_empty() {
+                return Err(Error::NotFound(aggregate_id));
+            }
+            Ok(events)
+        })
+        .await
+    }
+
+    async fn select_many_events(
+        &self,
+        aggregate_name: String,
+        aggregate_ids: Vec<Uuid>,
+    ) -> Result<Vec<SerializedEnvelope>, Error> {
+        let tenant_id = self.tenant_id.as_u128();
+
+        self.run(move |database| {
+            let tx = database.begin_read().map_err(transaction)?;
+            let states = tx.open_table(STREAM_STATES).map_err(execution)?;
+            let table = tx.open_table(EVENTS).map_err(execution)?;
+
+            let mut events = Vec::new();
+            for aggregate_id in aggregate_ids.into_iter().collect::<HashSet<_>>() {
+                let stream = (tenant_id, aggregate_name.as_str(), aggregate_id.as_u128());
+                if select_stream_state(&states, stream)?.is_none() {
+                    events.append(&mut select_stream(&table, stream)?);
+                }
+            }
+            Ok(events)
+        })
+        .await
+    }
+
+    // events of every stream of the aggregate type without a state, i.e. neither tombstoned
+    // nor archived
+    async fn select_active_events(
+        &self,
+        aggregate_name: String,
+    ) -> Result<Vec<SerializedEnvelope>, Error> {
+        let tenant_id = self.tenant_id.as_u128();
+
+        self.run(move |database| {
+            let tx = database.begin_read().map_err(transaction)?;
+            let states = tx.open_table(STREAM_STATES).map_err(execution)?;
+            let table = tx.open_table(EVENTS).map_err(execution)?;
+
+            let mut events = Vec::new();
+            let all_streams = (tenant_id, aggregate_name.as_str(), u128::MIN, i64::MIN)
+                ..=(tenant_id, aggregate_name.as_str(), u128::MAX, i64::MAX);
+            for entry in table.range(all_streams).map_err(execution)? {
+                let (key, event) = entry.map_err(execution)?;
+                let (_, _, aggregate_id, _) = key.value();
+                if select_stream_state(&states, (tenant_id, aggregate_name.as_str(), aggregate_id))?
+                    .is_none()
+                {
+                    events.push(deserialize(event.value().1)?);
+                }
+            }
+            Ok(events)
+        })
+        .await
+    }
+
+    async fn tombstone_stream(
+        &self,
+        aggregate_name: String,
+        aggregate_id: Uuid,
+    ) -> Result<(), Error> {
+        let tenant_id = self.tenant_id.as_u128();
+
+        self.run(move |database| {
+            let tx = database.begin_write().map_err(transaction)?;
+            {
+                let stream = (tenant_id, aggregate_name.as_str(), aggregate_id.as_u128());
+                let mut states = tx.open_table(STREAM_STATES).map_err(execution)?;
+
+                match select_stream_state(&states, stream)? {
+                    Some(StreamState::Tombstoned) => return Ok(()),
+                    Some(StreamState::Archived) => return Err(Error::Archived(aggregate_id)),
+                    None => (),
+                }
+                if select_stream(&tx.open_table(EVENTS).map_err(execution)?, stream)?.is_empty() {
+                    return Err(Error::NotFound(aggregate_id));
+                }
+
+                states
+                    .insert(stream, StreamState::Tombstoned.as_str())
+                    .map_err(execution)?;
+            }
+            tx.commit().map_err(transaction)
+        })
+        .await
+    }
+
+    async fn archive_stream(
+        &self,
+        aggregate_name: String,
+        aggregate_id: Uuid,
+    ) -> Result<(), Error> {
+        let tenant_id = self.tenant_id.as_u128();
+
+        self.run(move |database| {
+            let tx = database.begin_write().map_err(transaction)?;
+            {
+                let stream = (tenant_id, aggregate_name.as_str(), aggregate_id.as_u128());
+                let mut states = tx.open_table(STREAM_STATES).map_err(execution)?;
+
+                let state = select_stream_state(&states, stream)?;
+                if state == Some(StreamState::Archived) {
+                    return Ok(());
+                }
+
+                let mut archived = tx.open_table(ARCHIVED_EVENTS).map_err(execution)?;
+                let moved = remove_stream(&tx, stream, |key, event| {
+                    archived.insert(key, event).map(|_| ()).map_err(execution)
+                })?;
+                if moved == 0 {
+                    return Err(Error::NotFound(aggregate_id));
+                }
+
+                // tombstoned streams stay tombstoned
+                if state.is_none() {
+                    states
+                        .insert(stream, StreamState::Archived.as_str())
+                        .map_err(execution)?;
+                }
+            }
+            tx.commit().map_err(transaction)
+        })
+        .await
+    }
+
+    async fn delete_stream(&self, aggregate_name: String, aggregate_id: Uuid) -> Result<(), Error> {
+        let tenant_id = self.tenant_id.as_u128();
+
+        self.run(move |database| {
+            let tx = database.begin_write().map_err(transaction)?;
+            {
+                let stream = (tenant_id, aggregate_name.as_str(), aggregate_id.as_u128());
+
+                let deleted = remove_stream(&tx, stream, |_, _| Ok(()))?;
+
+                let mut archived = tx.open_table(ARCHIVED_EVENTS).map_err(execution)?;
+                let archived_keys = stream_keys(&archived, stream)?;
+                for (tenant_id, aggregate_name, aggregate_id, sequence) in &archived_keys {
+                    archived
+                        .remove((
+                            *tenant_id,
+                            aggregate_name.as_str(),
+                            *aggregate_id,
+                            *sequence,
+                        ))
+                        .map_err(execution)?;
+                }
+
+                let state = tx
+                    .open_table(STREAM_STATES)
+                    .map_err(execution)?
+                    .remove(stream)
+                    .map_err(execution)?
+                    .is_some();
+
+                let mut keys = tx.open_table(IDEMPOTENCY_KEYS).map_err(execution)?;
+                let mut idempotency_keys = Vec::new();
+                for entry in keys
+                    .range((tenant_id, aggregate_name.as_str(), "")..)
+                    .map_err(execution)?
+                {
+                    let (key, saved_aggregate_id) = entry.map_err(execution)?;
+                    let (key_tenant_id, key_aggregate_name, idempotency_key) = key.value();
+                    if (key_tenant_id, key_aggregate_name) != (tenant_id, aggregate_name.as_str()) {
+                        break;
+                    }
+                    if saved_aggregate_id.value() == stream.2 {
+                        idempotency_keys.push(idempotency_key.to_string());
+                    }
+                }
+                for idempotency_key in &idempotency_keys {
+                    keys.remove((tenant_id, aggregate_name.as_str(), idempotency_key.as_str()))
+                        .map_err(execution)?;
+                }
+
+                let mut reserved_keys = tx.open_table(RESERVED_KEYS).map_err(execution)?;
+                let mut held_keys = Vec::new();
+                for entry in reserved_keys
+                    .range((tenant_id, aggregate_name.as_str(), "", "")..)
+                    .map_err(execution)?
+                {
+                    let (key, holder) = entry.map_err(execution)?;
+                    let (key_tenant_id, key_aggregate_name, scope, reserved_key) = key.value();
+                    if (key_tenant_id, key_aggregate_name) != (tenant_id, aggregate_name.as_str()) {
+                        break;
+                    }
+                    if holder.value() == stream.2 {
+                        held_keys.push((scope.to_string(), reserved_key.to_string()));
+                    }
+                }
+                for (scope, reserved_key) in &held_keys {
+                    reserved_keys
+                        .remove((
+                            tenant_id,
+                            aggregate_name.as_str(),
+                            scope.as_str(),
+                            reserved_key.as_str(),
+                        ))
+                        .map_err(execution)?;
+                }
+
+                if deleted == 0 && archived_keys.is_empty() && !state {
+                    return Err(Error::NotFound(aggregate_id));
+                }
+            }
+            tx.commit().map_err(transaction)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl<A> Repository<A> for RedbRepository
+where
+    A: EventSourced,
+{
+    async fn save(&mut self, aggregate: &mut A) -> Result<(), Error> {
+        let pending_events = aggregate.drain_pending_events();
+
+        instrumentation::save::<A, _>(
+            BACKEND,
+            aggregate.get_id(),
+            aggregate.get_sequence(),
+            pending_events.len(),
+            async {
+                let serialized_events = pending_events
+                    .into_iter()
+                    .map(|mut envelope| {
+                        envelope.tenant_id = self.tenant_id;
+                        SerializedEnvelope::try_from(envelope)
+                    })
+                    .collect::<Result<Vec<SerializedEnvelope>, Error>>()?;
+
+                if serialized_events.is_empty() {
+                    return Ok(());
+                }
+
+                self.insert_events(
+                    A::get_name(),
+                    aggregate.get_id(),
+                    serialized_events,
+                    None,
+                    Reservations::default(),
+                )
+                .await
+                .map(|_| ())
+            },
+        )
+        .await
+    }
+
+    async fn find_all_events(&self, aggregate_id: &Uuid) -> Result<Vec<Envelope<A>>, Error> {
+        instrumentation::find_all_events(BACKEND, aggregate_id, async {
+            self.select_events(A::get_name(), *aggregate_id)
+                .await?
+                .into_iter()
+                .map(Envelope::try_from)
+                .collect::<Result<Vec<Envelope<A>>, Error>>()
+        })
+        .await
+    }
+
+    async fn find_many_events(
+        &self,
+        aggregate_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<Envelope<A>>>, Error> {
+        instrumentation::find_many_events(BACKEND, aggregate_ids.len(), async {
+            let events = self
+                .select_many_events(A::get_name(), aggregate_ids.to_vec())
+                .await?;
+
+            group_by_aggregate(events)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl<A> StreamLifecycle<A> for RedbRepository
+where
+    A: EventSourced,
+{
+    async fn tombstone(&mut self, aggregate_id: &Uuid) -> Result<(), Error> {
+        self.tombstone_stream(A::get_name(), *aggregate_id).await
+    }
+
+    async fn archive(&mut self, aggregate_id: &Uuid) -> Result<(), Error> {
+        self.archive_stream(A::get_name(), *aggregate_id).await
+    }
+
+    async fn delete(&mut self, aggregate_id: &Uuid) -> Result<(), Error> {
+        self.delete_stream(A::get_name(), *aggregate_id).await
+    }
+}
+
+#[async_trait]
+impl<A> IdempotentRepository<A> for RedbRepository
+where
+    A: EventSourced,
+{
+    async fn save_with_key(
+        &mut self,
+        aggregate: &mut A,
+        idempotency_key: &str,
+    ) -> Result<SaveOutcome, Error> {
+        let pending_events = aggregate.drain_pending_events();
+
+        instrumentation::save::<A, _>(
+            BACKEND,
+            aggregate.get_id(),
+            aggregate.get_sequence(),
+            pending_events.len(),
+            async {
+                let serialized_events = pending_events
+                    .into_iter()
+                    .map(|mut envelope| {
+                        envelope.tenant_id = self.tenant_id;
+                        SerializedEnvelope::try_from(envelope)
+                    })
+                    .collect::<Result<Vec<SerializedEnvelope>, Error>>()?;
+
+                if serialized_events.is_empty() {
+                    return Ok(SaveOutcome::Saved);
+                }
+
+                self.insert_events(
+                    A::get_name(),
+                    aggregate.get_id(),
+                    serialized_events,
+                    Some(idempotency_key.to_string()),
+                    Reservations::default(),
+                )
+                .await
+            },
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl<A> ReservingRepository<A> for RedbRepository
+where
+    A: EventSourced,
+{
+    async fn save_with_reservations(
+        &mut self,
+        aggregate: &mut A,
+        reservations: &Reservations,
+    ) -> Result<(), Error> {
+        let pending_events = aggregate.drain_pending_events();
+
+        instrumentation::save::<A, _>(
+            BACKEND,
+            aggregate.get_id(),
+            aggregate.get_sequence(),
+            pending_events.len(),
+            async {
+                let serialized_events = pending_events
+                    .into_iter()
+                    .map(|mut envelope| {
+                        envelope.tenant_id = self.tenant_id;
+                        SerializedEnvelope::try_from(envelope)
+                    })
+                    .collect::<Result<Vec<SerializedEnvelope>, Error>>()?;
+
+                if serialized_events.is_empty() {
+                    return Ok(());
+                }
+
+                self.insert_events(
+                    A::get_name(),
+                    aggregate.get_id(),
+                    serialized_events,
+                    None,
+                    reservations.clone(),
+                )
+                .await
+                .map(|_| ())
+            },
+        )
+        .await
+    }
+
+    async fn find_reservation(&self, scope: &str, key: &str) -> Result<Option<Uuid>, Error> {
+        let tenant_id = self.tenant_id.as_u128();
+        let aggregate_name = A::get_name();
+        let scope = scope.to_string();
+        let key = key.to_string();
+
+        self.run(move |database| {
+            let tx = database.begin_read().map_err(transaction)?;
+            let reserved_keys = tx.open_table(RESERVED_KEYS).map_err(execution)?;
+
+            Ok(reserved_keys
+                .get((
+                    tenant_id,
+                    aggregate_name.as_str(),
+                    scope.as_str(),
+                    key.as_str(),
+                ))
+                .map_err(execution)?
+                .map(|holder| Uuid::from_u128(holder.value())))
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl<A> StreamQuery<A> for RedbRepository
+where
+    A: EventSourced,
+{
+    async fn list_aggregate_ids(&self, page: Page) -> Result<Vec<Uuid>, Error> {
+        let aggregate_ids = self
+            .select_active_events(A::get_name())
+            .await?
+            .into_iter()
+            .map(|event| event.aggregate_id)
+            .collect::<BTreeSet<Uuid>>();
+
+        Ok(paginate(aggregate_ids.into_iter().collect(), page))
+    }
+
+    async fn count_streams(&self) -> Result<i64, Error> {
+        let aggregate_ids = self
+            .select_active_events(A::get_name())
+            .await?
+            .into_iter()
+            .map(|event| event.aggregate_id)
+            .collect::<HashSet<Uuid>>();
+
+        Ok(aggregate_ids.len() as i64)
+    }
+
+    async fn find_events(
+        &self,
+        filter: &EventFilter,
+        page: Page,
+    ) -> Result<Vec<Envelope<A>>, Error> {
+        let mut events = self
+            .select_active_events(A::get_name())
+            .await?
+            .into_iter()
+            .filter(|event| filter.matches(event))
+            .collect::<Vec<SerializedEnvelope>>();
+        events.sort_by_key(|event| {
+            (
+                event.created_at,
+                event.aggregate_id,
+                event.aggregate_sequence,
+            )
+        });
+
+        paginate(events, page)
+            .into_iter()
+            .map(Envelope::try_from)
+            .collect()
+    }
+}
+
+fn select_stream_state(
+    states: &impl ReadableTable<StreamKey<'static>, &'static str>,
+    stream: StreamKey,
+) -> Result<Option<StreamState>, Error> {
+    states
+        .get(stream)
+        .map_err(execution)?
+        .map(|state| StreamState::try_from(state.value()))
+        .transpose()
+}
+
+fn select_stream(
+    table: &impl ReadableTable<EventKey<'static>, StoredEvent<'static>>,
+    stream: StreamKey,
+) -> Result<Vec<SerializedEnvelope>, Error> {
+    let mut events = Vec::new();
+    for entry in table
+        .range((stream.0, stream.1, stream.2, i64::MIN)..=(stream.0, stream.1, stream.2, i64::MAX))
+        .map_err(execution)?
+    {
+        let (_, event) = entry.map_err(execution)?;
+        events.push(deserialize(event.value().1)?);
+    }
+    Ok(events)
+}
+
+fn stream_keys(
+    table: &impl ReadableTable<EventKey<'static>, StoredEvent<'static>>,
+    stream: StreamKey,
+) -> Result<Vec<(u128, String, u128, i64)>, Error> {
+    let mut keys = Vec::new();
+    for entry in table
+        .range((stream.0, stream.1, stream.2, i64::MIN)..=(stream.0, stream.1, stream.2, i64::MAX))
+        .map_err(execution)?
+    {
+        let (key, _) = entry.map_err(execution)?;
+        let (tenant_id, aggregate_name, aggregate_id, sequence) = key.value();
+        keys.push((
+            tenant_id,
+            aggregate_name.to_string(),
+            aggregate_id,
+            sequence,
+        ));
+    }
+    Ok(keys)
+}
+
+// Removes the live events of the stream along with their positions, handing each one over
+// before it goes, and returns how many were removed
+fn remove_stream(
+    tx: &::redb::WriteTransaction,
+    stream: StreamKey,
+    mut removed: impl FnMut(EventKey, StoredEvent) -> Result<(), Error>,
+) -> Result<usize, Error> {
+    let mut table = tx.open_table(EVENTS).map_err(execution)?;
+    let mut positions = tx.open_table(POSITIONS).map_err(execution)?;
+
+    let keys = stream_keys(&table, stream)?;
+    for (tenant_id, aggregate_name, aggregate_id, sequence) in &keys {
+        let key = (
+            *tenant_id,
+            aggregate_name.as_str(),
+            *aggregate_id,
+            *sequence,
+        );
+        if let Some(event) = table.remove(key).map_err(execution)? {
+            let event = event.value();
+            positions.remove(event.0).map_err(execution)?;
+            removed(key, event)?;
+        }
+    }
+    Ok(keys.len())
+}
+
+fn serialize(event: &SerializedEnvelope) -> Result<Vec<u8>, Error> {
+    serde_json::to_vec(event).map_err(|error| Error::Serialization(Box::new(error)))
+}
+
+fn deserialize(event: &[u8]) -> Result<SerializedEnvelope, Error> {
+    serde_json::from_slice(event).map_err(|error| Error::Deserialization(Box::new(error)))
+}
+
+fn execution(error: impl Into<::redb::Error>) -> Error {
+    Error::Execution(Box::new(error.into()))
+}
+
+fn transaction(error: impl Into<::redb::Error>) -> Error {
+    Error::Transaction(Box::new(error.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::NamedTempFile;
+    use uuid::Uuid;
+
+    use crate::aggregate::*;
+    use crate::repository::conformance;
+    use crate::repository::redb::*;
+    use crate::test::*;
+
+    // the file is removed when the guard is dropped at the end of the test
+    fn repository() -> (RedbRepository, NamedTempFile) {
+        let file = NamedTempFile::new().unwrap();
+        (RedbRepository::open(file.path()).unwrap(), file)
+    }
+
+    #[tokio::test]
+    async fn redb_repository_conforms_to_repository_behaviours() {
+        let (repository, _file) = repository();
+        conformance::run_all(repository).await;
+    }
+
+    #[tokio::test]
+    async fn redb_repository_conforms_to_stream_lifecycle_behaviours() {
+        let (repository, _file) = repository();
+        conformance::run_lifecycle(repository).await;
+    }
+
+    #[tokio::test]
+    async fn redb_repository_conforms_to_stream_query_behaviours() {
+        let (repository, _file) = repository();
+        conformance::run_query(repository).await;
+    }
+
+    #[tokio::test]
+    async fn redb_repository_conforms_to_tenancy_behaviours() {
+        let (repository, _file) = repository();
+        conformance::run_tenancy(|tenant_id| repository.clone().for_tenant(tenant_id)).await;
+    }
+
+    #[tokio::test]
+    async fn redb_repository_conforms_to_idempotency_behaviours() {
+        let (repository, _file) = repository();
+        conformance::run_idempotency(repository).await;
+    }
+
+    #[tokio::test]
+    async fn redb_repository_conforms_to_reservation_behaviours() {
+        let (repository, _file) = repository();
+        conformance::run_reservation(repository).await;
+    }
+
+    #[tokio::test]
+    async fn redb_events_are_found_after_their_global_position() {
+        let (mut repository, _file) = repository();
+        let ids = [Uuid::new_v4(), Uuid::new_v4()];
+        for id in ids {
+            let mut user = User::default();
+            user.update(UserEvent::UserRegistered { id }).await;
+            repository.save(&mut user).await.unwrap();
+        }
+        let mut user = User::load(repository.find_all_events(&ids[0]).await.unwrap()).await;
+        user.update(UserEvent::UserModified {
+            name: String::from("Arine"),
+        })
+        .await;
+        repository.save(&mut user).await.unwrap();
+
+        let all = repository.find_events_after::<User>(0, 10).await.unwrap();
+        let rest = repository
+            .find_events_after::<User>(all[0].position, 10)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            all.iter()
+                .map(|event| (
+                    event.envelope.aggregate_id,
+                    event.envelope.aggregate_sequence
+                ))
+                .collect::<Vec<_>>(),
+            vec![(ids[0], 1), (ids[1], 1), (ids[0], 2)]
+        );
+        assert!(all
+            .windows(2)
+            .all(|pair| pair[0].position < pair[1].position));
+        assert_eq!(rest.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn redb_events_survive_reopening_the_database() {
+        let path = std::env::temp_dir().join(format!("{}.redb", Uuid::new_v4()));
+        let id = Uuid::new_v4();
+        let mut user = User::default();
+        user.update(UserEvent::UserRegistered { id }).await;
+        RedbRepository::open(&path)
+            .unwrap()
+            .save(&mut user)
+            .await
+            .unwrap();
+
+        let envelopes: Vec<Envelope<User>> = RedbRepository::open(&path)
+            .unwrap()
+            .find_all_events(&id)
+            .await
+            .unwrap();
+
+        assert_eq!(envelopes.len(), 1);
+        std::fs::remove_file(path).unwrap();
+    }
+}
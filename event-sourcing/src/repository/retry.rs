@@ -0,0 +1,160 @@
+use std::future::Future;
+use std::time::Duration;
+
+use crate::repository::error::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(1),
+            multiplier: 2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            ..Self::default()
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self::new(1)
+    }
+
+    pub fn with_initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier.max(1);
+        self
+    }
+
+    pub fn get_max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    // attempt starts from 1, so the first retry waits for the initial backoff
+    pub fn get_backoff(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    pub(crate) async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            match operation().await {
+                Err(error) if error.is_transient() && attempt < self.max_attempts => {
+                    let backoff = self.get_backoff(attempt);
+                    #[cfg(feature = "instrumentation")]
+                    tracing::debug!(attempt, ?backoff, %error, "retrying transient repository error");
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    use crate::repository::error::Error;
+    use crate::repository::retry::*;
+
+    fn transient_error() -> Error {
+        Error::Execution(Box::new(sqlx::Error::PoolTimedOut))
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_the_maximum() {
+        let policy = RetryPolicy::new(10)
+            .with_initial_backoff(Duration::from_millis(10))
+            .with_max_backoff(Duration::from_millis(50))
+            .with_multiplier(2);
+
+        assert_eq!(policy.get_backoff(1), Duration::from_millis(10));
+        assert_eq!(policy.get_backoff(2), Duration::from_millis(20));
+        assert_eq!(policy.get_backoff(3), Duration::from_millis(40));
+        assert_eq!(policy.get_backoff(4), Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_retried_until_success() {
+        let attempts = AtomicU32::new(0);
+        let policy = RetryPolicy::new(3).with_initial_backoff(Duration::from_millis(1));
+
+        let result = policy
+            .run(|| async {
+                match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(transient_error()),
+                    _ => Ok(()),
+                }
+            })
+            .await;
+
+        assert!(result.is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_returned_when_attempts_are_exhausted() {
+        let attempts = AtomicU32::new(0);
+        let policy = RetryPolicy::new(3).with_initial_backoff(Duration::from_millis(1));
+
+        let result: Result<(), Error> = policy
+            .run(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(transient_error())
+            })
+            .await;
+
+        assert!(result.unwrap_err().is_transient());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn permanent_errors_are_not_retried() {
+        let attempts = AtomicU32::new(0);
+        let policy = RetryPolicy::new(3).with_initial_backoff(Duration::from_millis(1));
+
+        let result: Result<(), Error> = policy
+            .run(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(Error::Execution(Box::new(sqlx::Error::RowNotFound)))
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use uuid::Uuid;
@@ -10,6 +13,7 @@ use crate::repository::error::Error;
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SerializedEnvelope {
     pub id: Uuid,
+    pub tenant_id: Uuid,
     pub aggregate_name: String,
     pub aggregate_id: Uuid,
     pub aggregate_sequence: i64,
@@ -17,6 +21,25 @@ pub struct SerializedEnvelope {
     pub event_version: String,
     pub event_payload: Value,
     pub metadata: Value,
+    pub created_at: SystemTime,
+}
+
+impl SerializedEnvelope {
+    // derives another event of the same stream with a new ID, e.g. when splitting an event
+    pub fn with_event(
+        &self,
+        event_name: impl Into<String>,
+        event_version: impl Into<String>,
+        event_payload: Value,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            event_name: event_name.into(),
+            event_version: event_version.into(),
+            event_payload,
+            ..self.clone()
+        }
+    }
 }
 
 impl<A> TryFrom<Envelope<A>> for SerializedEnvelope
@@ -25,9 +48,24 @@ where
 {
     type Error = Error;
 
+    #[cfg_attr(
+        feature = "instrumentation",
+        tracing::instrument(
+            name = "event_sourcing.serialize",
+            level = "debug",
+            skip_all,
+            fields(
+                aggregate_name = %A::get_name(),
+                aggregate_id = %envelope.aggregate_id,
+                sequence = envelope.aggregate_sequence,
+            ),
+            err,
+        )
+    )]
     fn try_from(envelope: Envelope<A>) -> Result<Self, Self::Error> {
         Ok(Self {
             id: envelope.id,
+            tenant_id: envelope.tenant_id,
             aggregate_name: A::get_name(),
             aggregate_id: envelope.aggregate_id,
             aggregate_sequence: envelope.aggregate_sequence,
@@ -37,6 +75,7 @@ where
                 .map_err(|error| Error::Serialization(Box::new(error)))?,
             metadata: serde_json::to_value(&envelope.metadata)
                 .map_err(|error| Error::Serialization(Box::new(error)))?,
+            created_at: envelope.created_at,
         })
     }
 }
@@ -47,19 +86,62 @@ where
 {
     type Error = Error;
 
+    #[cfg_attr(
+        feature = "instrumentation",
+        tracing::instrument(
+            name = "event_sourcing.deserialize",
+            level = "debug",
+            skip_all,
+            fields(
+                aggregate_name = %event.aggregate_name,
+                aggregate_id = %event.aggregate_id,
+                sequence = event.aggregate_sequence,
+                event_name = %event.event_name,
+                event_version = %event.event_version,
+            ),
+            err,
+        )
+    )]
     fn try_from(event: SerializedEnvelope) -> Result<Self, Self::Error> {
         Ok(Self {
             id: event.id,
+            tenant_id: event.tenant_id,
             aggregate_id: event.aggregate_id,
             aggregate_sequence: event.aggregate_sequence,
             event: serde_json::from_value(event.event_payload)
                 .map_err(|error| Error::Deserialization(Box::new(error)))?,
             metadata: serde_json::from_value(event.metadata)
                 .map_err(|error| Error::Deserialization(Box::new(error)))?,
+            created_at: event.created_at,
         })
     }
 }
 
+// SQL backends keep times with millisecond precision
+pub(crate) fn to_millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+pub(crate) fn from_millis(millis: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis.max(0) as u64)
+}
+
+// groups events read in sequence order by their aggregate, keeping the order
+pub(crate) fn group_by_aggregate<A: EventSourced>(
+    events: Vec<SerializedEnvelope>,
+) -> Result<HashMap<Uuid, Vec<Envelope<A>>>, Error> {
+    let mut streams: HashMap<Uuid, Vec<Envelope<A>>> = HashMap::new();
+    for event in events {
+        streams
+            .entry(event.aggregate_id)
+            .or_default()
+            .push(Envelope::try_from(event)?);
+    }
+    Ok(streams)
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
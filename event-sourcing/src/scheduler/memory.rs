@@ -0,0 +1,98 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::repository::error::Error;
+use crate::scheduler::{ScheduleStore, ScheduledMessage};
+
+#[derive(Debug, Clone, Default)]
+pub struct MemoryScheduleStore {
+    messages: Arc<RwLock<HashMap<Uuid, Entry>>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    message: ScheduledMessage,
+    locked_until: Option<SystemTime>,
+}
+
+#[async_trait]
+impl ScheduleStore for MemoryScheduleStore {
+    async fn schedule(&self, message: ScheduledMessage) -> Result<(), Error> {
+        let mut messages = self.messages.write().map_err(|_| Error::Unknown)?;
+
+        if messages.contains_key(&message.id) {
+            return Err(Error::Conflict(
+                format!("Message {} is already scheduled", message.id).into(),
+            ));
+        }
+
+        messages.insert(
+            message.id,
+            Entry {
+                message,
+                locked_until: None,
+            },
+        );
+        Ok(())
+    }
+
+    async fn cancel(&self, id: &Uuid) -> Result<(), Error> {
+        let mut messages = self.messages.write().map_err(|_| Error::Unknown)?;
+
+        messages.remove(id);
+        Ok(())
+    }
+
+    async fn claim_due(
+        &self,
+        now: SystemTime,
+        lease: Duration,
+        limit: i64,
+    ) -> Result<Vec<ScheduledMessage>, Error> {
+        let mut messages = self.messages.write().map_err(|_| Error::Unknown)?;
+
+        let mut due = messages
+            .values_mut()
+            .filter(|entry| {
+                entry.message.due_at <= now && entry.locked_until.is_none_or(|until| until <= now)
+            })
+            .collect::<Vec<_>>();
+        due.sort_by_key(|entry| entry.message.due_at);
+
+        Ok(due
+            .into_iter()
+            .take(limit.max(0) as usize)
+            .map(|entry| {
+                entry.locked_until = Some(now + lease);
+                entry.message.attempts += 1;
+                entry.message.clone()
+            })
+            .collect())
+    }
+
+    async fn complete(&self, id: &Uuid) -> Result<(), Error> {
+        let mut messages = self.messages.write().map_err(|_| Error::Unknown)?;
+
+        messages.remove(id);
+        Ok(())
+    }
+
+    async fn release(&self, message: &ScheduledMessage, due_at: SystemTime) -> Result<(), Error> {
+        let mut messages = self.messages.write().map_err(|_| Error::Unknown)?;
+
+        if let Some(entry) = messages
+            .get_mut(&message.id)
+            .filter(|entry| entry.message.attempts == message.attempts)
+        {
+            entry.message.due_at = due_at;
+            entry.locked_until = None;
+        }
+        Ok(())
+    }
+}
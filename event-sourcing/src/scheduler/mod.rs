@@ -0,0 +1,423 @@
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::repository::error::Error;
+use crate::repository::retry::RetryPolicy;
+
+pub mod memory;
+pub mod mysql;
+pub mod postgresql;
+
+const DEFAULT_LEASE: Duration = Duration::from_secs(60);
+const DEFAULT_BATCH_SIZE: i64 = 100;
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledMessage {
+    pub id: Uuid,
+    pub name: String,
+    pub payload: Value,
+    pub due_at: SystemTime,
+    // number of deliveries so far, including the current one once claimed
+    pub attempts: i32,
+}
+
+impl ScheduledMessage {
+    pub fn new<P: Serialize>(
+        name: impl Into<String>,
+        payload: &P,
+        due_at: SystemTime,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            payload: serde_json::to_value(payload)
+                .map_err(|error| Error::Serialization(Box::new(error)))?,
+            due_at,
+            attempts: 0,
+        })
+    }
+
+    // A known ID makes scheduling idempotent and lets the message be cancelled later,
+    // e.g. an ID derived from the aggregate the message is about
+    pub fn with_id(mut self, id: Uuid) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn get_payload<P: DeserializeOwned>(&self) -> Result<P, Error> {
+        serde_json::from_value(self.payload.clone())
+            .map_err(|error| Error::Deserialization(Box::new(error)))
+    }
+}
+
+// Persists scheduled messages. Claimed messages are hidden from other claims until their
+// lease runs out, so a message is delivered again if its worker stops before completing it.
+#[async_trait]
+pub trait ScheduleStore: Clone + Send + Sync {
+    // fails with a conflict when a message with the same ID is already scheduled
+    async fn schedule(&self, message: ScheduledMessage) -> Result<(), Error>;
+    async fn cancel(&self, id: &Uuid) -> Result<(), Error>;
+    async fn claim_due(
+        &self,
+        now: SystemTime,
+        lease: Duration,
+        limit: i64,
+    ) -> Result<Vec<ScheduledMessage>, Error>;
+    async fn complete(&self, id: &Uuid) -> Result<(), Error>;
+    // Makes a claimed message due again at the given time. Each claim counts an attempt, so a
+    // message claimed again after its lease ran out is left to the worker now holding it.
+    async fn release(&self, message: &ScheduledMessage, due_at: SystemTime) -> Result<(), Error>;
+}
+
+#[async_trait]
+pub trait ScheduleHandler: Send + Sync {
+    async fn handle(
+        &self,
+        message: &ScheduledMessage,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+}
+
+// Delivers due messages to the handler at least once. Messages are completed only after the
+// handler succeeds, and failed ones are released to be delivered again after a backoff.
+pub struct Scheduler<S: ScheduleStore, H: ScheduleHandler> {
+    store: S,
+    handler: H,
+    lease: Duration,
+    batch_size: i64,
+    poll_interval: Duration,
+    backoff: RetryPolicy,
+}
+
+impl<S: ScheduleStore, H: ScheduleHandler> Scheduler<S, H> {
+    pub fn new(store: S, handler: H) -> Self {
+        Self {
+            store,
+            handler,
+            lease: DEFAULT_LEASE,
+            batch_size: DEFAULT_BATCH_SIZE,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            backoff: RetryPolicy::default(),
+        }
+    }
+
+    // should be longer than the handler takes, otherwise messages are delivered twice
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: i64) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    // only the backoff of the policy is used, as failed messages are retried until handled
+    pub fn with_backoff(mut self, backoff: RetryPolicy) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    // Runs until the task is dropped. Store errors are logged and followed by a backoff, so an
+    // unavailable database delays the messages rather than stopping their delivery.
+    pub async fn run(&self) {
+        let mut failures = 0;
+
+        loop {
+            match self.run_due(SystemTime::now()).await {
+                Ok(handled) => {
+                    failures = 0;
+                    if handled == 0 {
+                        tokio::time::sleep(self.poll_interval).await;
+                    }
+                }
+                Err(_error) => {
+                    failures += 1;
+                    #[cfg(feature = "instrumentation")]
+                    tracing::warn!(failures, error = %_error, "scheduled messages could not be delivered");
+                    tokio::time::sleep(self.backoff.get_backoff(failures)).await;
+                }
+            }
+        }
+    }
+
+    // Delivers the messages due at the given time and returns how many were handled
+    pub async fn run_due(&self, now: SystemTime) -> Result<usize, Error> {
+        let messages = self
+            .store
+            .claim_due(now, self.lease, self.batch_size)
+            .await?;
+
+        let mut handled = 0;
+        for message in messages {
+            match self.handler.handle(&message).await {
+                Ok(()) => {
+                    self.store.complete(&message.id).await?;
+                    handled += 1;
+                }
+                Err(_error) => {
+                    #[cfg(feature = "instrumentation")]
+                    tracing::warn!(
+                        id = %message.id,
+                        name = %message.name,
+                        attempts = message.attempts,
+                        error = %_error,
+                        "scheduled message failed"
+                    );
+                    let backoff = self.backoff.get_backoff(message.attempts.max(1) as u32);
+                    self.store.release(&message, now + backoff).await?;
+                }
+            }
+        }
+
+        Ok(handled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use crate::scheduler::memory::MemoryScheduleStore;
+    use crate::scheduler::*;
+
+    #[derive(Default)]
+    struct RecordingHandler {
+        handled: Mutex<Vec<Uuid>>,
+        failures: AtomicU32,
+    }
+
+    #[async_trait]
+    impl ScheduleHandler for RecordingHandler {
+        async fn handle(
+            &self,
+            message: &ScheduledMessage,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                return Err("handler failed".into());
+            }
+            self.handled.lock().unwrap().push(message.get_payload()?);
+            Ok(())
+        }
+    }
+
+    // fails the given number of claims before passing them on to the memory store
+    #[derive(Clone, Default)]
+    struct UnavailableStore {
+        store: MemoryScheduleStore,
+        failures: Arc<AtomicU32>,
+    }
+
+    #[async_trait]
+    impl ScheduleStore for UnavailableStore {
+        async fn schedule(&self, message: ScheduledMessage) -> Result<(), Error> {
+            self.store.schedule(message).await
+        }
+
+        async fn cancel(&self, id: &Uuid) -> Result<(), Error> {
+            self.store.cancel(id).await
+        }
+
+        async fn claim_due(
+            &self,
+            now: SystemTime,
+            lease: Duration,
+            limit: i64,
+        ) -> Result<Vec<ScheduledMessage>, Error> {
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                return Err(Error::Unknown);
+            }
+            self.store.claim_due(now, lease, limit).await
+        }
+
+        async fn complete(&self, id: &Uuid) -> Result<(), Error> {
+            self.store.complete(id).await
+        }
+
+        async fn release(
+            &self,
+            message: &ScheduledMessage,
+            due_at: SystemTime,
+        ) -> Result<(), Error> {
+            self.store.release(message, due_at).await
+        }
+    }
+
+    fn message_due_at(due_at: SystemTime) -> ScheduledMessage {
+        ScheduledMessage::new("ExpireRegistration", &Uuid::new_v4(), due_at).unwrap()
+    }
+
+    #[tokio::test]
+    async fn messages_are_delivered_only_when_due() {
+        let now = SystemTime::now();
+        let store = MemoryScheduleStore::default();
+        let message = message_due_at(now + Duration::from_secs(60));
+        store.schedule(message.clone()).await.unwrap();
+        let scheduler = Scheduler::new(store, RecordingHandler::default());
+
+        assert_eq!(scheduler.run_due(now).await.unwrap(), 0);
+        assert_eq!(
+            scheduler
+                .run_due(now + Duration::from_secs(60))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            *scheduler.handler.handled.lock().unwrap(),
+            vec![message.get_payload::<Uuid>().unwrap()]
+        );
+        assert_eq!(
+            scheduler
+                .run_due(now + Duration::from_secs(120))
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_messages_are_delivered_again_after_backoff() {
+        let now = SystemTime::now();
+        let store = MemoryScheduleStore::default();
+        store.schedule(message_due_at(now)).await.unwrap();
+        let handler = RecordingHandler {
+            failures: AtomicU32::new(1),
+            ..RecordingHandler::default()
+        };
+        let scheduler = Scheduler::new(store, handler).with_backoff(
+            RetryPolicy::default()
+                .with_initial_backoff(Duration::from_secs(10))
+                .with_max_backoff(Duration::from_secs(60)),
+        );
+
+        assert_eq!(scheduler.run_due(now).await.unwrap(), 0);
+        assert_eq!(
+            scheduler
+                .run_due(now + Duration::from_secs(5))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            scheduler
+                .run_due(now + Duration::from_secs(10))
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn claimed_messages_are_delivered_again_when_the_lease_expires() {
+        let now = SystemTime::now();
+        let store = MemoryScheduleStore::default();
+        store.schedule(message_due_at(now)).await.unwrap();
+        let lease = Duration::from_secs(30);
+
+        // a worker claims the message and stops before completing it
+        let claimed = store.claim_due(now, lease, 10).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert!(store.claim_due(now, lease, 10).await.unwrap().is_empty());
+
+        let redelivered = store.claim_due(now + lease, lease, 10).await.unwrap();
+        assert_eq!(redelivered.len(), 1);
+        assert_eq!(redelivered[0].attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn scheduler_keeps_running_after_store_errors() {
+        let store = UnavailableStore {
+            failures: Arc::new(AtomicU32::new(2)),
+            ..UnavailableStore::default()
+        };
+        store
+            .schedule(message_due_at(SystemTime::now()))
+            .await
+            .unwrap();
+        let scheduler = Arc::new(
+            Scheduler::new(store, RecordingHandler::default())
+                .with_poll_interval(Duration::from_millis(1))
+                .with_backoff(
+                    RetryPolicy::default()
+                        .with_initial_backoff(Duration::from_millis(1))
+                        .with_max_backoff(Duration::from_millis(1)),
+                ),
+        );
+
+        let running = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.run().await }
+        });
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while scheduler.handler.handled.lock().unwrap().is_empty() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .unwrap();
+        running.abort();
+
+        assert_eq!(scheduler.store.failures.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn released_messages_keep_the_lease_of_a_later_claim() {
+        let now = SystemTime::now();
+        let store = MemoryScheduleStore::default();
+        store.schedule(message_due_at(now)).await.unwrap();
+        let lease = Duration::from_secs(30);
+
+        // the first worker outlives its lease and the message is claimed again
+        let expired = store.claim_due(now, lease, 10).await.unwrap().remove(0);
+        store.claim_due(now + lease, lease, 10).await.unwrap();
+
+        store.release(&expired, now + lease).await.unwrap();
+
+        assert!(store
+            .claim_due(now + lease, lease, 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancelled_messages_are_not_delivered() {
+        let now = SystemTime::now();
+        let store = MemoryScheduleStore::default();
+        let message = message_due_at(now);
+        store.schedule(message.clone()).await.unwrap();
+
+        store.cancel(&message.id).await.unwrap();
+
+        assert!(store
+            .claim_due(now, DEFAULT_LEASE, 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn messages_with_the_same_id_are_scheduled_once() {
+        let store = MemoryScheduleStore::default();
+        let message = message_due_at(SystemTime::now());
+        store.schedule(message.clone()).await.unwrap();
+
+        let result = store.schedule(message).await;
+
+        assert!(matches!(result, Err(Error::Conflict(..))));
+    }
+}
@@ -0,0 +1,508 @@
+// JSON Schemas of stored events for consumers outside Rust, derived from the event types.
+// The schemas describe `event_payload` as stored, i.e. with the variant name as external tag.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use jsonschema::JSONSchema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::aggregate::EventSourced;
+use crate::envelope::Envelope;
+use crate::event::DomainEvent;
+use crate::repository::error::Error;
+use crate::repository::interface::{
+    IdempotentRepository, Repository, Reservations, ReservingRepository, SaveOutcome,
+    StreamLifecycle, StreamQuery,
+};
+use crate::repository::query::{EventFilter, Page};
+
+const CATALOGUE_FILE: &str = "catalogue.json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchemaError {
+    #[error("No schema registered for {aggregate_name} event {event_name} {event_version}")]
+    Unregistered {
+        aggregate_name: String,
+        event_name: String,
+        event_version: String,
+    },
+
+    #[error("Payload of {aggregate_name} event {event_name} {event_version} does not match its schema: {}", reasons.join(", "))]
+    Invalid {
+        aggregate_name: String,
+        event_name: String,
+        event_version: String,
+        reasons: Vec<String>,
+    },
+
+    #[error("Schema of {aggregate_name} event {event_name} {event_version} cannot be compiled: {reason}")]
+    Compilation {
+        aggregate_name: String,
+        event_name: String,
+        event_version: String,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EventSchema {
+    pub aggregate_name: String,
+    pub event_name: String,
+    pub event_version: String,
+    pub schema: Value,
+}
+
+// keyed by aggregate name, event name and event version, as events are stored
+type SchemaKey = (String, String, String);
+
+#[derive(Default)]
+pub struct SchemaRegistry {
+    schemas: BTreeMap<SchemaKey, (EventSchema, JSONSchema)>,
+}
+
+impl SchemaRegistry {
+    // Registers the schema of the variant the given event is of, under the name and version
+    // the event reports. Every variant and version to be saved needs a sample registered.
+    pub fn register<A>(&mut self, event: &A::Event) -> Result<(), Error>
+    where
+        A: EventSourced,
+        A::Event: JsonSchema,
+    {
+        let payload =
+            serde_json::to_value(event).map_err(|error| Error::Serialization(Box::new(error)))?;
+        let root = serde_json::to_value(schemars::schema_for!(A::Event))
+            .map_err(|error| Error::Serialization(Box::new(error)))?;
+
+        let event_schema = EventSchema {
+            aggregate_name: A::get_name(),
+            event_name: event.get_name(),
+            event_version: event.get_version(),
+            schema: variant_schema(root, &payload),
+        };
+        let validator = JSONSchema::compile(&event_schema.schema).map_err(|error| {
+            Error::Serialization(Box::new(SchemaError::Compilation {
+                aggregate_name: event_schema.aggregate_name.clone(),
+                event_name: event_schema.event_name.clone(),
+                event_version: event_schema.event_version.clone(),
+                reason: error.to_string(),
+            }))
+        })?;
+
+        self.schemas.insert(
+            (
+                event_schema.aggregate_name.clone(),
+                event_schema.event_name.clone(),
+                event_schema.event_version.clone(),
+            ),
+            (event_schema, validator),
+        );
+        Ok(())
+    }
+
+    pub fn get(
+        &self,
+        aggregate_name: &str,
+        event_name: &str,
+        event_version: &str,
+    ) -> Option<&EventSchema> {
+        self.schemas
+            .get(&(
+                aggregate_name.to_string(),
+                event_name.to_string(),
+                event_version.to_string(),
+            ))
+            .map(|(event_schema, _)| event_schema)
+    }
+
+    // ordered by aggregate name, event name and event version
+    pub fn schemas(&self) -> impl Iterator<Item = &EventSchema> {
+        self.schemas.values().map(|(event_schema, _)| event_schema)
+    }
+
+    pub fn validate(
+        &self,
+        aggregate_name: &str,
+        event_name: &str,
+        event_version: &str,
+        payload: &Value,
+    ) -> Result<(), Error> {
+        let key = (
+            aggregate_name.to_string(),
+            event_name.to_string(),
+            event_version.to_string(),
+        );
+        let Some((_, validator)) = self.schemas.get(&key) else {
+            return Err(Error::Serialization(Box::new(SchemaError::Unregistered {
+                aggregate_name: key.0,
+                event_name: key.1,
+                event_version: key.2,
+            })));
+        };
+
+        validator.validate(payload).map_err(|errors| {
+            Error::Serialization(Box::new(SchemaError::Invalid {
+                reasons: errors
+                    .map(|error| format!("{} at '{}'", error, error.instance_path))
+                    .collect(),
+                aggregate_name: key.0.clone(),
+                event_name: key.1.clone(),
+                event_version: key.2.clone(),
+            }))
+        })
+    }
+
+    // Writes a file per event as <aggregate>/<event>-<version>.json, and a catalogue.json
+    // listing every event with the path of its schema
+    pub fn export(&self, directory: impl AsRef<Path>) -> std::io::Result<()> {
+        let directory = directory.as_ref();
+        let mut catalogue = Vec::new();
+
+        for event_schema in self.schemas() {
+            let path = Path::new(&event_schema.aggregate_name).join(format!(
+                "{}-{}.json",
+                event_schema.event_name, event_schema.event_version
+            ));
+            fs::create_dir_all(directory.join(&event_schema.aggregate_name))?;
+            fs::write(
+                directory.join(&path),
+                serde_json::to_vec_pretty(&event_schema.schema)?,
+            )?;
+
+            catalogue.push(serde_json::json!({
+                "aggregate_name": event_schema.aggregate_name,
+                "event_name": event_schema.event_name,
+                "event_version": event_schema.event_version,
+                "path": path,
+            }));
+        }
+
+        fs::create_dir_all(directory)?;
+        fs::write(
+            directory.join(CATALOGUE_FILE),
+            serde_json::to_vec_pretty(&catalogue)?,
+        )
+    }
+}
+
+// Picks the schema of the variant out of the schema of the whole enum, keeping the shared
+// definitions it refers to. Anything other than an externally tagged enum is kept whole.
+fn variant_schema(root: Value, payload: &Value) -> Value {
+    let tag = match payload {
+        Value::Object(map) if map.len() == 1 => map.keys().next().cloned(),
+        Value::String(tag) => Some(tag.clone()),
+        _ => None,
+    };
+    let variant = tag.and_then(|tag| {
+        root.get("oneOf")?
+            .as_array()?
+            .iter()
+            .find(|schema| is_variant(schema, &tag))
+            .cloned()
+    });
+
+    match (variant, root) {
+        (Some(Value::Object(mut variant)), Value::Object(root)) => {
+            for key in ["$schema", "definitions"] {
+                if let Some(value) = root.get(key) {
+                    variant.insert(key.to_string(), value.clone());
+                }
+            }
+            Value::Object(variant)
+        }
+        (_, root) => root,
+    }
+}
+
+fn is_variant(schema: &Value, tag: &str) -> bool {
+    ["required", "enum"].iter().any(|key| {
+        schema
+            .get(key)
+            .and_then(Value::as_array)
+            .is_some_and(|values| values.iter().any(|value| value == tag))
+    })
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Validation {
+    // validates in debug builds only, e.g. to catch drift in tests
+    #[default]
+    Debug,
+    // validates in every build
+    Strict,
+}
+
+// Validates pending events against the registry before saving them with the inner repository.
+// Events failing validation are left pending, and nothing is written.
+#[derive(Clone)]
+pub struct ValidatingRepository<R> {
+    repository: R,
+    registry: Arc<SchemaRegistry>,
+    validation: Validation,
+}
+
+impl<R> ValidatingRepository<R> {
+    pub fn new(repository: R, registry: Arc<SchemaRegistry>) -> Self {
+        Self {
+            repository,
+            registry,
+            validation: Validation::default(),
+        }
+    }
+
+    pub fn with_validation(mut self, validation: Validation) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    fn validate<A: EventSourced>(&self, aggregate: &A) -> Result<(), Error> {
+        if self.validation == Validation::Debug && !cfg!(debug_assertions) {
+            return Ok(());
+        }
+
+        for envelope in aggregate.get_pending_events() {
+            let payload = serde_json::to_value(&envelope.event)
+                .map_err(|error| Error::Serialization(Box::new(error)))?;
+            self.registry.validate(
+                &A::get_name(),
+                &envelope.event.get_name(),
+                &envelope.event.get_version(),
+                &payload,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<A, R> Repository<A> for ValidatingRepository<R>
+where
+    A: EventSourced,
+    R: Repository<A>,
+{
+    async fn save(&mut self, aggregate: &mut A) -> Result<(), Error> {
+        self.validate(aggregate)?;
+        self.repository.save(aggregate).await
+    }
+
+    async fn find_all_events(&self, aggregate_id: &Uuid) -> Result<Vec<Envelope<A>>, Error> {
+        self.repository.find_all_events(aggregate_id).await
+    }
+
+    async fn find_many_events(
+        &self,
+        aggregate_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<Envelope<A>>>, Error> {
+        self.repository.find_many_events(aggregate_ids).await
+    }
+}
+
+#[async_trait]
+impl<A, R> IdempotentRepository<A> for ValidatingRepository<R>
+where
+    A: EventSourced,
+    R: IdempotentRepository<A>,
+{
+    async fn save_with_key(
+        &mut self,
+        aggregate: &mut A,
+        idempotency_key: &str,
+    ) -> Result<SaveOutcome, Error> {
+        self.validate(aggregate)?;
+        self.repository
+            .save_with_key(aggregate, idempotency_key)
+            .await
+    }
+}
+
+#[async_trait]
+impl<A, R> ReservingRepository<A> for ValidatingRepository<R>
+where
+    A: EventSourced,
+    R: ReservingRepository<A>,
+{
+    async fn save_with_reservations(
+        &mut self,
+        aggregate: &mut A,
+        reservations: &Reservations,
+    ) -> Result<(), Error> {
+        self.validate(aggregate)?;
+        self.repository
+            .save_with_reservations(aggregate, reservations)
+            .await
+    }
+
+    async fn find_reservation(&self, scope: &str, key: &str) -> Result<Option<Uuid>, Error> {
+        self.repository.find_reservation(scope, key).await
+    }
+}
+
+#[async_trait]
+impl<A, R> StreamLifecycle<A> for ValidatingRepository<R>
+where
+    A: EventSourced,
+    R: StreamLifecycle<A>,
+{
+    async fn tombstone(&mut self, aggregate_id: &Uuid) -> Result<(), Error> {
+        self.repository.tombstone(aggregate_id).await
+    }
+
+    async fn archive(&mut self, aggregate_id: &Uuid) -> Result<(), Error> {
+        self.repository.archive(aggregate_id).await
+    }
+
+    async fn delete(&mut self, aggregate_id: &Uuid) -> Result<(), Error> {
+        self.repository.delete(aggregate_id).await
+    }
+}
+
+#[async_trait]
+impl<A, R> StreamQuery<A> for ValidatingRepository<R>
+where
+    A: EventSourced,
+    R: StreamQuery<A>,
+{
+    async fn list_aggregate_ids(&self, page: Page) -> Result<Vec<Uuid>, Error> {
+        self.repository.list_aggregate_ids(page).await
+    }
+
+    async fn count_streams(&self) -> Result<i64, Error> {
+        self.repository.count_streams().await
+    }
+
+    async fn find_events(
+        &self,
+        filter: &EventFilter,
+        page: Page,
+    ) -> Result<Vec<Envelope<A>>, Error> {
+        self.repository.find_events(filter, page).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::aggregate::EventSourced;
+    use crate::repository::memory::MemoryRepository;
+    use crate::schema::*;
+    use crate::test::*;
+
+    fn registry() -> SchemaRegistry {
+        let mut registry = SchemaRegistry::default();
+        registry
+            .register::<User>(&UserEvent::UserRegistered { id: Uuid::nil() })
+            .unwrap();
+        registry
+            .register::<User>(&UserEvent::UserModified {
+                name: String::new(),
+            })
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn registered_events_have_the_schema_of_their_variant() {
+        let registry = registry();
+
+        let schema = &registry
+            .get("User", "UserModified", "1.0.0")
+            .unwrap()
+            .schema;
+
+        assert_eq!(schema["required"], json!(["UserModified"]));
+        assert_eq!(
+            registry
+                .schemas()
+                .map(|event_schema| event_schema.event_name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["UserModified", "UserRegistered"]
+        );
+    }
+
+    #[test]
+    fn payloads_are_validated_against_their_schema() {
+        let registry = registry();
+
+        let valid = registry.validate(
+            "User",
+            "UserModified",
+            "1.0.0",
+            &json!({ "UserModified": { "name": "Arine" } }),
+        );
+        let invalid = registry.validate(
+            "User",
+            "UserModified",
+            "1.0.0",
+            &json!({ "UserModified": { "name": 1 } }),
+        );
+        let other_variant = registry.validate(
+            "User",
+            "UserModified",
+            "1.0.0",
+            &json!({ "UserRegistered": { "id": Uuid::new_v4() } }),
+        );
+        let unregistered = registry.validate(
+            "User",
+            "UserModified",
+            "2.0.0",
+            &json!({ "UserModified": { "name": "Arine" } }),
+        );
+
+        assert!(valid.is_ok());
+        assert!(matches!(invalid, Err(Error::Serialization(..))));
+        assert!(matches!(other_variant, Err(Error::Serialization(..))));
+        assert!(
+            matches!(unregistered, Err(Error::Serialization(error)) if error.to_string().starts_with("No schema registered"))
+        );
+    }
+
+    #[tokio::test]
+    async fn unregistered_events_are_not_saved_and_stay_pending() {
+        let mut registry = SchemaRegistry::default();
+        registry
+            .register::<User>(&UserEvent::UserRegistered { id: Uuid::nil() })
+            .unwrap();
+        let mut repository =
+            ValidatingRepository::new(MemoryRepository::default(), Arc::new(registry))
+                .with_validation(Validation::Strict);
+
+        let id = Uuid::new_v4();
+        let mut user = User::default();
+        user.update(UserEvent::UserRegistered { id }).await;
+        user.update(UserEvent::UserModified {
+            name: String::from("Arine"),
+        })
+        .await;
+        let result = repository.save(&mut user).await;
+
+        let found: Result<Vec<Envelope<User>>, Error> = repository.find_all_events(&id).await;
+        assert!(matches!(result, Err(Error::Serialization(..))));
+        assert_eq!(user.get_pending_events().len(), 2);
+        assert!(matches!(found, Err(Error::NotFound(..))));
+    }
+
+    #[test]
+    fn catalogue_is_exported_as_files() {
+        let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+
+        registry().export(&directory).unwrap();
+
+        let catalogue: Value =
+            serde_json::from_slice(&fs::read(directory.join(CATALOGUE_FILE)).unwrap()).unwrap();
+        let schema: Value = serde_json::from_slice(
+            &fs::read(directory.join("User/UserRegistered-1.0.0.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(catalogue.as_array().unwrap().len(), 2);
+        assert_eq!(schema["required"], json!(["UserRegistered"]));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -56,6 +56,7 @@ impl Display for UserError {
 impl std::error::Error for UserError {}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum UserEvent {
     UserRegistered { id: Uuid },
     UserModified { name: String },